@@ -0,0 +1,179 @@
+//! Moving the debug variant's DWARF into an external file.
+//!
+//! The DWARF in the debug wasm is usually many times larger than the code,
+//! and every copy of the debug wasm in the package (web-debug, bundler-debug,
+//! the standalone `-debug.wasm` and the base64 modules) carries it. Splitting
+//! follows Emscripten's `-gseparate-dwarf` layout, which Chrome's DWARF
+//! extension understands: the full wasm is written to a separate
+//! `.debug.wasm` file, and the runtime wasm keeps everything except the
+//! `.debug_*` sections plus an `external_debug_info` custom section holding
+//! the debug file's URL. DWARF addresses are offsets into the code section,
+//! which stripping custom sections doesn't change.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use super::targets::{WasmBindgenTarget, WasmVariant};
+use super::wasm_binary::{self, Section};
+
+/// Name of the custom section that points at the external debug file.
+const EXTERNAL_DEBUG_INFO: &str = "external_debug_info";
+
+/// Write the debug variant's DWARF to `debug_dir/{wasm_name}.debug.wasm` and
+/// strip it from the debug variant's web and bundler wasm.
+///
+/// `url` is recorded in the `external_debug_info` section. It defaults to the
+/// debug file's name, which devtools resolve relative to the wasm's URL.
+pub fn split(
+    wasm_bindgen_dir: &Path,
+    wasm_name: &str,
+    debug_dir: &Path,
+    url: Option<&str>,
+) -> Result<()> {
+    let bg_wasm = |target: WasmBindgenTarget| {
+        wasm_bindgen_dir
+            .join(format!(
+                "{}{}",
+                target.dir_name(),
+                WasmVariant::Debug.dir_suffix()
+            ))
+            .join(format!("{wasm_name}_bg.wasm"))
+    };
+
+    let web_wasm = bg_wasm(WasmBindgenTarget::Web);
+    if !web_wasm.exists() {
+        anyhow::bail!(
+            "--split-debug-info needs a debug variant. Pass --debug-profile or --debug-wasm."
+        );
+    }
+    let web_bytes = std::fs::read(&web_wasm).context("Failed to read debug wasm")?;
+    let web_sections = wasm_binary::sections(&web_bytes)?;
+    if !web_sections.iter().any(Section::is_dwarf) {
+        anyhow::bail!(
+            "The debug wasm has no DWARF to split. Build the debug variant with \
+             `debug = \"full\"` in its Cargo profile."
+        );
+    }
+
+    let file_name = format!("{wasm_name}.debug.wasm");
+    std::fs::create_dir_all(debug_dir)?;
+    let debug_file = debug_dir.join(&file_name);
+    std::fs::write(&debug_file, &web_bytes)
+        .with_context(|| format!("Failed to write {}", debug_file.display()))?;
+    let url = url.unwrap_or(&file_name);
+
+    let web_code = code_section(&web_sections);
+    for target in [WasmBindgenTarget::Web, WasmBindgenTarget::Bundler] {
+        let path = bg_wasm(target);
+        if !path.exists() {
+            continue;
+        }
+        let bytes = std::fs::read(&path)?;
+        let sections = wasm_binary::sections(&bytes)?;
+        if code_section(&sections) != web_code {
+            anyhow::bail!(
+                "The {target} and web debug wasm have different code, so they can't share \
+                 one external debug file"
+            );
+        }
+        let stripped = strip_dwarf(&sections, url);
+        std::fs::write(&path, &stripped)?;
+        println!(
+            "  Split DWARF out of {}: {} -> {} bytes",
+            path.display(),
+            bytes.len(),
+            stripped.len()
+        );
+    }
+
+    println!(
+        "  Wrote external debug info to {} (referenced as {url:?})",
+        debug_file.display()
+    );
+    Ok(())
+}
+
+fn code_section<'a>(sections: &[Section<'a>]) -> Option<&'a [u8]> {
+    sections
+        .iter()
+        .find(|s| s.id == wasm_binary::CODE_SECTION)
+        .map(|s| s.payload)
+}
+
+/// Drop the DWARF sections and any previous `external_debug_info` section,
+/// then append one pointing at `url`.
+fn strip_dwarf(sections: &[Section<'_>], url: &str) -> Vec<u8> {
+    let mut url_data = Vec::new();
+    wasm_binary::write_name(&mut url_data, url);
+    let payload = wasm_binary::custom_section_payload(EXTERNAL_DEBUG_INFO, &url_data);
+
+    let kept = sections
+        .iter()
+        .filter(|s| !s.is_dwarf() && s.custom_name() != Some(EXTERNAL_DEBUG_INFO))
+        .copied();
+    wasm_binary::encode(kept.chain([Section {
+        id: 0,
+        payload: &payload,
+    }]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_dwarf_keeps_code_and_names() {
+        let type_section = [0x01, 0x60, 0x00, 0x00];
+        let code = [0x01, 0x02, 0x00, 0x0b];
+        let name = wasm_binary::custom_section_payload("name", &[0x00]);
+        let line = wasm_binary::custom_section_payload(".debug_line", &[1, 2, 3]);
+        let info = wasm_binary::custom_section_payload(".debug_info", &[4, 5, 6]);
+        let original = wasm_binary::encode([
+            Section {
+                id: 1,
+                payload: &type_section,
+            },
+            Section {
+                id: wasm_binary::CODE_SECTION,
+                payload: &code,
+            },
+            Section {
+                id: 0,
+                payload: &name,
+            },
+            Section {
+                id: 0,
+                payload: &line,
+            },
+            Section {
+                id: 0,
+                payload: &info,
+            },
+        ]);
+
+        let sections = wasm_binary::sections(&original).unwrap();
+        let stripped = strip_dwarf(&sections, "https://example.com/lib.debug.wasm");
+        let stripped = wasm_binary::sections(&stripped).unwrap();
+
+        assert!(!stripped.iter().any(Section::is_dwarf));
+        assert_eq!(code_section(&stripped), Some(&code[..]));
+        assert!(stripped.iter().any(|s| s.custom_name() == Some("name")));
+
+        let external = stripped.last().unwrap();
+        assert_eq!(external.custom_name(), Some(EXTERNAL_DEBUG_INFO));
+        let mut url = Vec::new();
+        wasm_binary::write_name(&mut url, "https://example.com/lib.debug.wasm");
+        assert!(external.payload.ends_with(&url));
+
+        // Splitting again replaces the reference instead of adding a second one
+        let again = strip_dwarf(&stripped, "lib.debug.wasm");
+        let again = wasm_binary::sections(&again).unwrap();
+        assert_eq!(
+            again
+                .iter()
+                .filter(|s| s.custom_name() == Some(EXTERNAL_DEBUG_INFO))
+                .count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! TypeScript declarations for hand-written modules (custom subpaths and the
+//! facade): hand-written `types` are copied, TypeScript sources go through
+//! tsc, and JavaScript sources without `types` get none.
+//!
+//! Every `.d.ts` an export points at also gets `.d.mts` and `.d.cts` copies.
+//! In a `"type": "module"` package a `.d.ts` describes an ES module, so
+//! TypeScript's node16 resolution reads it as the wrong format for the
+//! `require` side; the exports pair each format's JS with its own copy.
+
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use serde_json::{Value, json};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::LazyLock;
+
+use super::entrypoints::find_node_bin;
+
+/// A quoted relative specifier, the quotes captured on either side.
+static RELATIVE_SPECIFIER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(["'])(\.\.?/[^"'\s]*)(["'])"#).unwrap());
+
+/// Module format of a JS file or of the declarations describing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Esm,
+    Cjs,
+}
+
+impl Format {
+    /// JS extension of a module in this format, whatever the package type.
+    pub fn js_extension(self) -> &'static str {
+        match self {
+            Format::Esm => ".mjs",
+            Format::Cjs => ".cjs",
+        }
+    }
+
+    pub fn declaration_extension(self) -> &'static str {
+        match self {
+            Format::Esm => ".d.mts",
+            Format::Cjs => ".d.cts",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Esm => "ESM",
+            Format::Cjs => "CJS",
+        })
+    }
+}
+
+/// Name of the declaration file written for `source`, or None for a
+/// JavaScript source without `types`.
+pub fn file_name(source: &Path, types: Option<&Path>) -> Option<String> {
+    if let Some(types) = types {
+        return Some(types.file_name()?.to_str()?.to_string());
+    }
+    let stem = source.file_stem()?.to_str()?;
+    let extension = match source.extension()?.to_str()? {
+        "ts" | "tsx" => "d.ts",
+        "mts" => "d.mts",
+        "cts" => "d.cts",
+        _ => return None,
+    };
+    Some(format!("{stem}.{extension}"))
+}
+
+/// Write the declarations of `source` into `types_dir`, replacing what's
+/// there. `paths` maps module specifiers to declaration files for tsc, like
+/// tsconfig's `paths`. `label` names the module in errors.
+pub fn write(
+    source: &Path,
+    types: Option<&Path>,
+    types_dir: &Path,
+    paths: Value,
+    label: &str,
+) -> Result<()> {
+    let Some(file_name) = file_name(source, types) else {
+        return Ok(());
+    };
+    let _ = std::fs::remove_dir_all(types_dir);
+    std::fs::create_dir_all(types_dir)?;
+
+    if let Some(types) = types {
+        std::fs::copy(types, types_dir.join(&file_name))
+            .with_context(|| format!("Failed to copy {}", types.display()))?;
+        return Ok(());
+    }
+
+    let tsc = find_node_bin("tsc").with_context(|| {
+        format!(
+            "tsc not found, needed for the declarations of {label}. Install it \
+             (npm install --save-dev typescript) or set `types`."
+        )
+    })?;
+
+    let source = source.canonicalize()?;
+    let tsconfig = json!({
+        "compilerOptions": {
+            "declaration": true,
+            "emitDeclarationOnly": true,
+            "skipLibCheck": true,
+            "module": "esnext",
+            "moduleResolution": "bundler",
+            "target": "es2022",
+            "rootDir": source.parent(),
+            "outDir": types_dir.canonicalize()?,
+            "paths": paths,
+        },
+        "files": [source],
+    });
+    let tsconfig_path = types_dir.join("tsconfig.json");
+    std::fs::write(&tsconfig_path, serde_json::to_string_pretty(&tsconfig)?)?;
+
+    let status = Command::new(&tsc)
+        .arg("--project")
+        .arg(&tsconfig_path)
+        .status()
+        .context("Failed to run tsc")?;
+    std::fs::remove_file(&tsconfig_path)?;
+    if !status.success() {
+        anyhow::bail!("tsc failed for {label}");
+    }
+    if !types_dir.join(&file_name).exists() {
+        anyhow::bail!("tsc did not write {file_name} for {label}");
+    }
+    Ok(())
+}
+
+/// The package's npm name, including any scope.
+pub fn npm_name(package_json: &Path) -> Result<String> {
+    let content = std::fs::read_to_string(package_json).context("Failed to read package.json")?;
+    let parsed: Value = serde_json::from_str(&content).context("Failed to parse package.json")?;
+    parsed["name"]
+        .as_str()
+        .map(String::from)
+        .context("package.json has no name, which declarations import the package by")
+}
+
+/// Declarations of `types` for `format`: `X.d.mts` or `X.d.cts` for a
+/// `X.d.ts`. Other declaration files describe a single format and are used
+/// as they are.
+pub fn format_path(types: &Path, format: Format) -> PathBuf {
+    let types_str = types.to_string_lossy();
+    match types_str.strip_suffix(".d.ts") {
+        Some(stem) => PathBuf::from(format!("{stem}{}", format.declaration_extension())),
+        None => types.to_path_buf(),
+    }
+}
+
+/// Write the `.d.mts` and `.d.cts` copies of the `.d.ts` at `path`, or of
+/// every `.d.ts` under it for a directory. Relative imports in the copies
+/// point at the same format's declarations. A missing `path` is skipped.
+pub fn write_formats(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            write_formats(&entry?.path())?;
+        }
+        return Ok(());
+    }
+    if !path.to_string_lossy().ends_with(".d.ts") {
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    for format in [Format::Esm, Format::Cjs] {
+        std::fs::write(
+            format_path(path, format),
+            rewrite_relative_imports(&content, dir, format),
+        )?;
+    }
+    Ok(())
+}
+
+/// Point the relative specifiers in `content`, declarations in `dir`, at
+/// `format`'s modules: `./x.js` and an extensionless `./x` (with
+/// `./x.d.ts` or `./x/index.d.ts` next to it) become `./x.mjs` or `./x.cjs`.
+fn rewrite_relative_imports(content: &str, dir: &Path, format: Format) -> String {
+    RELATIVE_SPECIFIER
+        .replace_all(content, |c: &Captures| {
+            let (quote, path) = (&c[1], &c[2]);
+            if quote != &c[3] {
+                return c[0].to_string();
+            }
+            let extension = format.js_extension();
+            let rewritten = if let Some(stem) = path.strip_suffix(".js") {
+                format!("{stem}{extension}")
+            } else if path
+                .rsplit('/')
+                .next()
+                .is_some_and(|name| name.contains('.'))
+            {
+                path.to_string()
+            } else if dir.join(format!("{path}.d.ts")).is_file() {
+                format!("{path}{extension}")
+            } else if dir.join(path).join("index.d.ts").is_file() {
+                format!("{}/index{extension}", path.trim_end_matches('/'))
+            } else {
+                path.to_string()
+            };
+            format!("{quote}{rewritten}{quote}")
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_declarations() {
+        assert_eq!(
+            format_path(Path::new("facade-types/x.d.ts"), Format::Esm),
+            PathBuf::from("facade-types/x.d.mts")
+        );
+        assert_eq!(
+            format_path(Path::new("index.d.ts"), Format::Cjs),
+            PathBuf::from("index.d.cts")
+        );
+        assert_eq!(
+            format_path(Path::new("x.d.mts"), Format::Cjs),
+            PathBuf::from("x.d.mts")
+        );
+
+        let dir = std::env::temp_dir().join("wasm-bodge-test-declarations");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("util.d.ts"), "").unwrap();
+        std::fs::write(dir.join("nested/index.d.ts"), "").unwrap();
+
+        let content = "export * from './index.js';\n\
+                       import { a } from \"../index.js\";\n\
+                       import type { U } from './util';\n\
+                       export * from './nested';\n\
+                       export * from './missing';\n\
+                       /// <reference path=\"./globals.d.ts\" />\n\
+                       import 'some-package';\n";
+        assert_eq!(
+            rewrite_relative_imports(content, &dir, Format::Cjs),
+            "export * from './index.cjs';\n\
+             import { a } from \"../index.cjs\";\n\
+             import type { U } from './util.cjs';\n\
+             export * from './nested/index.cjs';\n\
+             export * from './missing';\n\
+             /// <reference path=\"./globals.d.ts\" />\n\
+             import 'some-package';\n"
+        );
+
+        std::fs::write(
+            dir.join("nested/index.d.ts"),
+            "export * from '../util.js';\n",
+        )
+        .unwrap();
+        write_formats(&dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("nested/index.d.mts")).unwrap(),
+            "export * from '../util.mjs';\n"
+        );
+        assert!(dir.join("util.d.cts").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,182 @@
+//! Reading DWARF line tables out of a wasm binary.
+//!
+//! rustc emits DWARF into `.debug_*` custom sections. In wasm, DWARF
+//! addresses are byte offsets from the start of the code section's payload.
+
+use anyhow::{Context, Result};
+use gimli::{EndianSlice, LittleEndian};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::wasm_binary::{self, Section};
+
+/// One row of the line table: code at `address` came from `line`:`column`
+/// of `files[file]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineEntry {
+    /// Offset from the start of the code section payload
+    pub address: u64,
+    pub file: usize,
+    /// 1-based
+    pub line: u32,
+    /// 1-based, or 0 if unknown
+    pub column: u32,
+}
+
+/// The merged line tables of every compilation unit, sorted by address.
+#[derive(Debug, Default)]
+pub struct LineTable {
+    pub files: Vec<String>,
+    pub entries: Vec<LineEntry>,
+}
+
+/// Read the line tables from the `.debug_*` sections of `sections`.
+///
+/// Sequences starting at address 0 are skipped: the linker points DWARF for
+/// functions it garbage-collected there, and no instruction can live at
+/// offset 0 (it holds the function count).
+pub fn line_table(sections: &[Section<'_>]) -> Result<LineTable> {
+    let dwarf = gimli::Dwarf::load(|id| -> Result<_> {
+        let data = wasm_binary::custom_section(sections, id.name()).unwrap_or(&[]);
+        Ok(EndianSlice::new(data, LittleEndian))
+    })?;
+
+    let mut table = LineTable::default();
+    let mut file_indices: HashMap<String, usize> = HashMap::new();
+
+    let mut units = dwarf.units();
+    while let Some(header) = units.next().context("Failed to read DWARF unit")? {
+        let unit = dwarf.unit(header)?;
+        let Some(program) = unit.line_program.clone() else {
+            continue;
+        };
+
+        let mut rows = program.rows();
+        let mut sequence = Vec::new();
+        while let Some((header, row)) = rows.next_row()? {
+            if row.end_sequence() {
+                if sequence.first().is_some_and(|e: &LineEntry| e.address != 0) {
+                    table.entries.append(&mut sequence);
+                }
+                sequence.clear();
+                continue;
+            }
+            let Some(line) = row.line() else {
+                continue;
+            };
+            let Some(file) = row.file(header) else {
+                continue;
+            };
+
+            let path = file_path(&dwarf, &unit, header, file)?;
+            let next_index = file_indices.len();
+            let file = *file_indices.entry(path.clone()).or_insert_with(|| {
+                table.files.push(path);
+                next_index
+            });
+
+            let column = match row.column() {
+                gimli::ColumnType::LeftEdge => 0,
+                gimli::ColumnType::Column(column) => column.get() as u32,
+            };
+            sequence.push(LineEntry {
+                address: row.address(),
+                file,
+                line: line.get() as u32,
+                column,
+            });
+        }
+    }
+
+    table.entries.sort_by_key(|e| e.address);
+    Ok(table)
+}
+
+/// Where a function was declared, from its `DW_TAG_subprogram` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subprogram {
+    /// Offset of the function's code from the start of the code section payload
+    pub low_pc: u64,
+    pub file: String,
+    /// 1-based
+    pub line: u32,
+}
+
+/// Read the declaration location of every function with code, sorted by
+/// `low_pc`. As with [`line_table`], entries at address 0 are skipped.
+pub fn subprograms(sections: &[Section<'_>]) -> Result<Vec<Subprogram>> {
+    let dwarf = gimli::Dwarf::load(|id| -> Result<_> {
+        let data = wasm_binary::custom_section(sections, id.name()).unwrap_or(&[]);
+        Ok(EndianSlice::new(data, LittleEndian))
+    })?;
+
+    let mut subprograms = Vec::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next().context("Failed to read DWARF unit")? {
+        let unit = dwarf.unit(header)?;
+        let Some(program) = &unit.line_program else {
+            continue;
+        };
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_subprogram {
+                continue;
+            }
+            let Some(gimli::AttributeValue::Addr(low_pc)) =
+                entry.attr_value(gimli::DW_AT_low_pc)?
+            else {
+                continue;
+            };
+            let Some(gimli::AttributeValue::FileIndex(file)) =
+                entry.attr_value(gimli::DW_AT_decl_file)?
+            else {
+                continue;
+            };
+            let Some(line) = entry
+                .attr_value(gimli::DW_AT_decl_line)?
+                .and_then(|v| v.udata_value())
+            else {
+                continue;
+            };
+            let Some(file) = program.header().file(file) else {
+                continue;
+            };
+            if low_pc == 0 {
+                continue;
+            }
+            subprograms.push(Subprogram {
+                low_pc,
+                file: file_path(&dwarf, &unit, program.header(), file)?,
+                line: line as u32,
+            });
+        }
+    }
+
+    subprograms.sort_by_key(|s| s.low_pc);
+    Ok(subprograms)
+}
+
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+
+/// Absolute path of a line program file: comp_dir + directory + name.
+fn file_path(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &gimli::Unit<Reader<'_>>,
+    header: &gimli::LineProgramHeader<Reader<'_>>,
+    file: &gimli::FileEntry<Reader<'_>>,
+) -> Result<String> {
+    let mut path = PathBuf::new();
+    if let Some(comp_dir) = &unit.comp_dir {
+        path.push(comp_dir.to_string_lossy().as_ref());
+    }
+    if let Some(dir) = file.directory(header) {
+        path.push(dwarf.attr_string(unit, dir)?.to_string_lossy().as_ref());
+    }
+    path.push(
+        dwarf
+            .attr_string(unit, file.path_name())?
+            .to_string_lossy()
+            .as_ref(),
+    );
+    Ok(path.to_string_lossy().into_owned())
+}
@@ -1,12 +1,39 @@
 use anyhow::{Context, Result};
-use heck::ToPascalCase;
-use std::path::Path;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use super::targets::{self, Environment, WasmVariant};
+use super::facade::{self, Facade};
+use super::source_map;
+use super::targets::{self, EntrypointOptions, Environment, WasmVariant};
+use crate::config::{BuildConfig, IifeConfig, IifeFormat, WasmCompression};
 
-/// Generate all entrypoints (ESM, CJS, IIFE) for every variant that was built.
-pub fn generate(out_dir: &Path, crate_name: &str) -> Result<()> {
+/// Base64 decoder imported by the base64-embedded entrypoints.
+const DECODE_BASE64_JS: &str = include_str!("js/decode_base64.js");
+
+/// Gzip decoder imported by the base64-embedded entrypoints when the
+/// embedded wasm is compressed.
+const GUNZIP_JS: &str = include_str!("js/gunzip.js");
+
+/// Lookup of the page-provided wasm, imported by the base64 entrypoints with
+/// `--wasm-source-override`.
+const WASM_SOURCE_JS: &str = include_str!("js/wasm_source.js");
+
+/// Generate all entrypoints (ESM, CJS, IIFE) for every variant that was
+/// built, returning the options each variant's entrypoints were generated with.
+///
+/// Whether a variant's base64 entrypoints initialize asynchronously is decided
+/// per variant from its wasm size, so e.g. a large debug build can go async
+/// while the optimized one stays synchronous.
+///
+/// With a facade, every entrypoint but `/slim` is the facade, importing the
+/// entrypoint it replaces as its bindings module.
+pub fn generate(
+    crate_name: &str,
+    config: &BuildConfig,
+) -> Result<Vec<(WasmVariant, EntrypointOptions)>> {
+    let out_dir = config.out_dir.as_path();
+    let compression = config.compress_wasm;
     let wasm_name = crate_name.replace('-', "_");
     let esm_dir = out_dir.join("esm");
     let cjs_dir = out_dir.join("cjs");
@@ -16,6 +43,30 @@ pub fn generate(out_dir: &Path, crate_name: &str) -> Result<()> {
     std::fs::create_dir_all(&cjs_dir)?;
     std::fs::create_dir_all(&iife_dir)?;
 
+    std::fs::write(
+        out_dir.join(targets::paths::decode_base64_esm()),
+        DECODE_BASE64_JS,
+    )?;
+    if compression == Some(WasmCompression::Gzip) {
+        std::fs::write(out_dir.join(targets::paths::gunzip_esm()), GUNZIP_JS)?;
+    }
+    if config.wasm_source_override {
+        std::fs::write(
+            out_dir.join(targets::paths::wasm_source_esm()),
+            WASM_SOURCE_JS,
+        )?;
+    }
+
+    let esbuild = find_esbuild()?;
+    let package = read_package_json(&config.package_json)?;
+    let facade = config
+        .file
+        .facade
+        .as_ref()
+        .map(|facade_config| Facade::compile(&esbuild, facade_config))
+        .transpose()?;
+
+    let mut variant_options = Vec::new();
     for variant in WasmVariant::all() {
         // Skip variants whose wasm-bindgen output isn't present (e.g. a
         // --wasm-bindgen-tar tarball that only contains the optimized dirs).
@@ -24,78 +75,285 @@ pub fn generate(out_dir: &Path, crate_name: &str) -> Result<()> {
             continue;
         }
 
+        let wasm = std::fs::read(web_dir.join(format!("{}_bg.wasm", wasm_name)))
+            .context("Failed to read web target wasm")?;
+        let wasm_size = wasm.len() as u64;
+        let minify = config.minify.applies_to(*variant);
+        let options = EntrypointOptions {
+            compression,
+            async_init: config
+                .async_init
+                .applies_to(wasm_size, config.async_init_threshold),
+            source_map: config.js_source_maps.applies_to(*variant, minify),
+            minify,
+            shared_node_instance: config.shared_node_instance,
+            wasm_source_override: config.wasm_source_override,
+        };
+        if options.async_init {
+            println!(
+                "  Using async init for {} base64 entrypoints ({} byte wasm)",
+                variant, wasm_size
+            );
+        }
+        variant_options.push((*variant, options));
+
+        let build_info = targets::generate_build_info(
+            *variant,
+            package["name"].as_str(),
+            package["version"].as_str(),
+            &format!("{:x}", Sha256::digest(&wasm)),
+        );
+        std::fs::write(
+            out_dir.join(targets::paths::build_info_esm(*variant)),
+            format!("export const buildInfo = {build_info};\n"),
+        )?;
+        std::fs::write(
+            out_dir.join(targets::paths::build_info_cjs(*variant)),
+            format!("module.exports.buildInfo = {build_info};\n"),
+        )?;
+
         println!("  Generating ESM entrypoints ({})...", variant,);
         for env in Environment::all() {
-            let content = targets::generate_esm_entrypoint(*env, &wasm_name, *variant);
+            let content = targets::generate_esm_entrypoint(*env, &wasm_name, *variant, &options);
             let path = out_dir.join(targets::paths::esm_entrypoint(*env, *variant));
-            std::fs::write(&path, content)?;
+            match &facade {
+                // Re-exporting the CJS entrypoint re-exports its facade too
+                Some(facade) if facade::wraps(*env) && !options.reexports_cjs(*env) => {
+                    let bindings = out_dir.join(targets::paths::esm_bindings(*env, *variant));
+                    write_esm(&bindings, &content, &options)?;
+                    let specifier = facade::esm_bindings_specifier(*env, *variant);
+                    let ready = options.exports_ready(*env);
+                    write_esm(&path, &facade.esm_entrypoint(&specifier, ready), &options)?;
+                }
+                _ => write_esm(&path, &content, &options)?,
+            }
+        }
+
+        if options.shared_node_instance {
+            write_esm(
+                &out_dir.join(targets::paths::esm_node_slim(*variant)),
+                &targets::generate_esm_node_slim(*variant),
+                &options,
+            )?;
         }
 
         println!("  Generating CJS entrypoints ({})...", variant,);
         for env in Environment::all() {
             if let Some(content) = targets::generate_cjs_entrypoint(*env, &wasm_name, *variant) {
                 let path = out_dir.join(targets::paths::cjs_entrypoint(*env, *variant));
-                std::fs::write(&path, content)?;
+                match &facade {
+                    Some(facade) if facade::wraps(*env) => {
+                        let bindings = out_dir.join(targets::paths::cjs_bindings(*env, *variant));
+                        std::fs::write(&bindings, content)?;
+                        let specifier = facade::cjs_bindings_specifier(*env, *variant);
+                        std::fs::write(&path, facade.cjs_entrypoint(&specifier))?;
+                    }
+                    _ => std::fs::write(&path, content)?,
+                }
             }
         }
     }
 
     // Bundle entrypoints that need it (IIFE and CJS versions of ESM-only targets)
     println!("  Bundling with esbuild...");
-    bundle_with_esbuild(out_dir, crate_name)?;
+    bundle_with_esbuild(
+        &esbuild,
+        out_dir,
+        crate_name,
+        &variant_options,
+        facade.as_ref(),
+        &config.file.iife,
+    )?;
 
-    Ok(())
+    Ok(variant_options)
 }
 
-fn bundle_with_esbuild(out_dir: &Path, crate_name: &str) -> Result<()> {
-    let esbuild = find_esbuild()?;
+/// Write an ESM entrypoint, with an identity source map when `options` say.
+fn write_esm(path: &Path, content: &str, options: &EntrypointOptions) -> Result<()> {
+    if options.source_map {
+        source_map::write_js_identity_mapped(path, content)
+    } else {
+        Ok(std::fs::write(path, content)?)
+    }
+}
+
+/// The package.json being built, whose name and version `__wasmBodge`
+/// reports.
+fn read_package_json(package_json: &Path) -> Result<serde_json::Value> {
+    let content = std::fs::read_to_string(package_json).context("Failed to read package.json")?;
+    serde_json::from_str(&content).context("Failed to parse package.json")
+}
+
+fn bundle_with_esbuild(
+    esbuild: &str,
+    out_dir: &Path,
+    crate_name: &str,
+    variant_options: &[(WasmVariant, EntrypointOptions)],
+    facade: Option<&Facade>,
+    iife: &IifeConfig,
+) -> Result<()> {
     let wasm_name = crate_name.replace('-', "_");
-    let global_name = crate_name.to_pascal_case();
 
     // Per-variant bundles: web-bindings.cjs, IIFE, and CJS-for-ESM-envs.
     // Each variant has its own web-bindings.cjs because wasm-opt renames wasm
     // exports in the optimized variant, causing the wasm-bindgen JS to diverge
     // between variants.
-    for variant in WasmVariant::all() {
+    for (variant, options) in variant_options {
         let web_dir = out_dir.join(format!("wasm_bindgen/web{}", variant.dir_suffix()));
-        if !web_dir.exists() {
-            continue;
-        }
+        let mut sizes = BundleSizes::default();
 
         // Bundle this variant's web-bindings.cjs from its own wasm-bindgen JS.
         let web_js = web_dir.join(format!("{}.js", wasm_name));
         let web_bindings_cjs = out_dir.join(targets::paths::cjs_web_bindings(*variant));
-        run_esbuild(&esbuild, &web_js, &web_bindings_cjs, "cjs", None)?;
+        run_esbuild(
+            esbuild,
+            &web_js,
+            &web_bindings_cjs,
+            "cjs",
+            &[],
+            options,
+            &mut sizes,
+        )?;
 
-        // Bundle IIFE from this variant's web entrypoint
-        let esm_web = out_dir.join(targets::paths::esm_entrypoint(Environment::Web, *variant));
+        // Bundle the IIFE from this variant's IIFE environment input
+        let esm_iife = bundle_input(
+            out_dir,
+            Environment::Iife,
+            &wasm_name,
+            *variant,
+            options,
+            facade,
+        )?;
         let iife_output = out_dir.join(targets::paths::iife_bundle(*variant));
-        let iife_global = if variant.is_debug() {
-            format!("{}Debug", global_name)
-        } else {
-            global_name.clone()
+        run_esbuild(
+            esbuild,
+            &esm_iife,
+            &iife_output,
+            "iife",
+            &iife_args(iife, &iife.global_name(crate_name, *variant)),
+            options,
+            &mut sizes,
+        )?;
+
+        // A facade's npm imports are the package's dependencies, which
+        // `require` finds at runtime, as when `facade::bundle` bundles the
+        // facade itself. The IIFE has no such luck.
+        let cjs_args = match facade {
+            Some(_) => vec!["--packages=external".to_string()],
+            None => Vec::new(),
         };
-        run_esbuild(&esbuild, &esm_web, &iife_output, "iife", Some(&iife_global))?;
 
         // Bundle CJS versions for environments that need it
         for env in Environment::all() {
             if env.needs_cjs_bundle() {
-                let esm_path = out_dir.join(targets::paths::esm_entrypoint(*env, *variant));
+                let esm_path = bundle_input(out_dir, *env, &wasm_name, *variant, options, facade)?;
                 let cjs_path = out_dir.join(targets::paths::cjs_entrypoint(*env, *variant));
-                run_esbuild(&esbuild, &esm_path, &cjs_path, "cjs", None)?;
+                run_esbuild(
+                    esbuild, &esm_path, &cjs_path, "cjs", &cjs_args, options, &mut sizes,
+                )?;
+            }
+        }
+
+        for env in Environment::all().iter().chain([&Environment::Iife]) {
+            for temp_input in [
+                targets::paths::esm_bundle_input(*env, *variant),
+                targets::paths::esm_facade_input(*env, *variant),
+            ] {
+                let temp_input = out_dir.join(temp_input);
+                if temp_input.exists() {
+                    std::fs::remove_file(temp_input)?;
+                }
             }
         }
+
+        if options.minify {
+            println!(
+                "  Minified {} bundles: {} bytes of JS -> {} bytes ({:.0}%), not counting the embedded wasm",
+                if variant.is_debug() {
+                    "debug"
+                } else {
+                    "optimized"
+                },
+                sizes.inputs,
+                sizes.minified,
+                100.0 * sizes.minified as f64 / sizes.inputs.max(1) as f64,
+            );
+        }
     }
 
     Ok(())
 }
 
+/// The ESM file esbuild should bundle for `env`: the shipped entrypoint, or a
+/// temporary top-level-await-free copy for async entrypoints. With a facade,
+/// a temporary facade entrypoint over whichever of those is the bindings.
+fn bundle_input(
+    out_dir: &Path,
+    env: Environment,
+    wasm_name: &str,
+    variant: WasmVariant,
+    options: &EntrypointOptions,
+    facade: Option<&Facade>,
+) -> Result<PathBuf> {
+    let bindings = match targets::generate_esm_bundle_input(env, wasm_name, variant, options) {
+        Some(content) => {
+            let path = out_dir.join(targets::paths::esm_bundle_input(env, variant));
+            std::fs::write(&path, content)?;
+            path
+        }
+        None if facade.is_some() => out_dir.join(targets::paths::esm_bindings(env, variant)),
+        None => out_dir.join(targets::paths::esm_entrypoint(env, variant)),
+    };
+    let Some(facade) = facade else {
+        return Ok(bindings);
+    };
+
+    let path = out_dir.join(targets::paths::esm_facade_input(env, variant));
+    let content = facade.esm_entrypoint(
+        &facade::relative_specifier(&bindings),
+        options.exports_ready(env),
+    );
+    std::fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// esbuild arguments for the IIFE bundle assigning its exports to `global`:
+/// the global name, or the UMD wrapper, and the `[iife]` target.
+fn iife_args(iife: &IifeConfig, global: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    match iife.format {
+        IifeFormat::Iife => args.push(format!("--global-name={}", global)),
+        IifeFormat::Umd => {
+            let (banner, footer) = targets::generate_umd_wrapper(global);
+            args.push(format!("--global-name={}", targets::UMD_EXPORTS));
+            args.push(format!("--banner:js={}", banner));
+            args.push(format!("--footer:js={}", footer));
+        }
+    }
+    if let Some(target) = &iife.target {
+        args.push(format!("--target={}", target));
+    }
+    args
+}
+
+/// Total size of a variant's minified esbuild bundles and of their inputs,
+/// without the base64 wasm they embed.
+#[derive(Default)]
+struct BundleSizes {
+    inputs: u64,
+    minified: u64,
+}
+
+/// Bundle `input` into `output`, minified and with a source map as `options`
+/// say, adding its sizes to `sizes` when minified.
 fn run_esbuild(
     esbuild: &str,
     input: &Path,
     output: &Path,
     format: &str,
-    global_name: Option<&str>,
+    extra_args: &[String],
+    options: &EntrypointOptions,
+    sizes: &mut BundleSizes,
 ) -> Result<()> {
     let mut args = vec![
         input.to_str().unwrap().to_string(),
@@ -110,44 +368,88 @@ fn run_esbuild(
         args.push("--platform=node".to_string());
     }
 
-    if let Some(name) = global_name {
-        args.push(format!("--global-name={}", name));
+    args.extend_from_slice(extra_args);
+
+    // The metafile has the inputs' sizes, to report what minifying saves
+    // without bundling twice. Minifying would also rename wasm-bindgen's
+    // exported classes, which shows in their `name` and `constructor.name`.
+    let metafile = output.with_extension("meta.json");
+    if options.minify {
+        args.push("--minify".to_string());
+        args.push("--keep-names".to_string());
+        args.push(format!("--metafile={}", metafile.display()));
+    }
+
+    // A separate `<output>.map` with the sources embedded, linked by a
+    // sourceMappingURL comment. Inline maps would double the bundle size.
+    if options.source_map {
+        args.push("--sourcemap=linked".to_string());
     }
 
     let status = Command::new(esbuild)
         .args(&args)
         .status()
         .with_context(|| format!("Failed to run esbuild for {} bundle", format))?;
-
     if !status.success() {
         anyhow::bail!("esbuild {} bundle failed", format);
     }
 
+    if options.minify {
+        let meta: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&metafile)?)
+            .context("Failed to parse esbuild's metafile")?;
+        std::fs::remove_file(&metafile)?;
+        // The embedded base64 wasm is most of a bundle and minifying can't
+        // shrink it, so it's left out of both sides
+        sizes.inputs += meta["inputs"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(path, _)| !is_wasm_base64(path))
+            .filter_map(|(_, input)| input["bytes"].as_u64())
+            .sum::<u64>();
+        let embedded_wasm = meta["outputs"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(_, output)| output["inputs"].as_object())
+            .flatten()
+            .filter(|(path, _)| is_wasm_base64(path))
+            .filter_map(|(_, input)| input["bytesInOutput"].as_u64())
+            .sum::<u64>();
+        sizes.minified += std::fs::metadata(output)?
+            .len()
+            .saturating_sub(embedded_wasm);
+    }
     Ok(())
 }
 
-fn find_esbuild() -> Result<String> {
+/// Whether a metafile input is a base64 wasm module.
+fn is_wasm_base64(path: &str) -> bool {
+    path.ends_with("wasm-base64.js") || path.ends_with("wasm-base64-gzip.js")
+}
+
+pub(super) fn find_esbuild() -> Result<String> {
+    find_node_bin("esbuild").context(
+        "esbuild not found. Please install it:\n  \
+         npm install -g esbuild\n  \
+         or: npm install --save-dev esbuild",
+    )
+}
+
+/// Find a Node.js tool's executable on the PATH or in a nearby node_modules.
+pub(super) fn find_node_bin(name: &str) -> Option<String> {
     // Try common locations
     let candidates = [
-        "esbuild",                      // System PATH
-        "./node_modules/.bin/esbuild",  // Local node_modules
-        "../node_modules/.bin/esbuild", // Parent node_modules
+        name.to_string(),                       // System PATH
+        format!("./node_modules/.bin/{name}"),  // Local node_modules
+        format!("../node_modules/.bin/{name}"), // Parent node_modules
     ];
 
-    for candidate in candidates {
-        if Command::new(candidate)
+    candidates.into_iter().find(|candidate| {
+        Command::new(candidate)
             .arg("--version")
             .output()
             .map(|o| o.status.success())
             .unwrap_or(false)
-        {
-            return Ok(candidate.to_string());
-        }
-    }
-
-    anyhow::bail!(
-        "esbuild not found. Please install it:\n  \
-         npm install -g esbuild\n  \
-         or: npm install --save-dev esbuild"
-    )
+    })
 }
@@ -0,0 +1,312 @@
+//! The facade: a hand-written module, configured as `[facade]`, that the
+//! generated entrypoints re-export instead of the raw wasm-bindgen exports.
+//!
+//! The facade imports the bindings from `wasm-bodge:bindings`. esbuild
+//! bundles it once per format with that specifier and npm imports left
+//! external, and each entrypoint is that bundle with the specifier pointing
+//! at the environment's bindings module: what the entrypoint would have been
+//! without a facade, moved to `{env}.bindings.js`. Being the facade's
+//! dependency, the bindings module finishes initializing (top-level await
+//! included) before any of the facade's code runs.
+
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::LazyLock;
+
+use super::declarations;
+use super::targets::{self, Environment, WasmVariant};
+use crate::config::FacadeConfig;
+
+/// Specifier the facade imports the bindings from.
+pub const BINDINGS_SPECIFIER: &str = "wasm-bodge:bindings";
+
+/// Exports the entrypoints add to the facade's, so the facade can't have them.
+const RESERVED_EXPORTS: [&str; 2] = ["ready", "__wasmBodge"];
+
+/// esbuild's (double-quoted) static and dynamic imports and requires of the
+/// bindings, the part before the specifier captured.
+static BINDINGS_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r#"(\bfrom\s*|\bimport\s*(?:\(\s*)?|\brequire\s*\(\s*)"{}""#,
+        regex::escape(BINDINGS_SPECIFIER)
+    ))
+    .unwrap()
+});
+
+/// The export clause esbuild ends an ESM bundle with.
+static EXPORT_CLAUSE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^export\s*\{([^}]*)\};").unwrap());
+
+/// The facade bundled to ESM and CJS, still importing [`BINDINGS_SPECIFIER`].
+pub struct Facade {
+    esm: String,
+    cjs: String,
+}
+
+impl Facade {
+    pub fn compile(esbuild: &str, config: &FacadeConfig) -> Result<Self> {
+        println!("  Bundling facade {}...", config.source.display());
+        let facade = Self {
+            esm: bundle(esbuild, &config.source, "esm")?,
+            cjs: bundle(esbuild, &config.source, "cjs")?,
+        };
+        if !BINDINGS_IMPORT.is_match(&facade.esm) {
+            anyhow::bail!(
+                "The facade {} doesn't import the bindings from \"{BINDINGS_SPECIFIER}\"",
+                config.source.display()
+            );
+        }
+        if let Some(name) = reserved_export(&facade.esm) {
+            anyhow::bail!(
+                "The facade {} exports `{name}`, which wasm-bodge adds to the entrypoints itself",
+                config.source.display()
+            );
+        }
+        Ok(facade)
+    }
+
+    /// ESM entrypoint: the facade over the bindings module `bindings` (a
+    /// relative specifier), also re-exporting its `__wasmBodge` and its
+    /// `ready` promise if it has one.
+    pub fn esm_entrypoint(&self, bindings: &str, ready: bool) -> String {
+        let mut content = replace_specifier(&self.esm, bindings);
+        if ready {
+            content.push_str(&format!("export {{ ready }} from '{bindings}';\n"));
+        }
+        content.push_str(&format!("export {{ __wasmBodge }} from '{bindings}';\n"));
+        content
+    }
+
+    /// CJS entrypoint: the facade over the bindings module `bindings`, also
+    /// re-exporting its `__wasmBodge`.
+    pub fn cjs_entrypoint(&self, bindings: &str) -> String {
+        let mut content = replace_specifier(&self.cjs, bindings);
+        content.push_str(&format!(
+            "module.exports.__wasmBodge = require('{bindings}').__wasmBodge;\n"
+        ));
+        content
+    }
+}
+
+/// Check the facade's files exist before building.
+pub fn validate(config: &FacadeConfig) -> Result<()> {
+    if !config.source.is_file() {
+        anyhow::bail!("Facade source {} not found", config.source.display());
+    }
+    if let Some(types) = config.types.as_ref().filter(|t| !t.is_file()) {
+        anyhow::bail!("Facade types {} not found", types.display());
+    }
+    Ok(())
+}
+
+/// Whether `env`'s entrypoints re-export the facade. `/slim` stays the raw
+/// bindings: its users initialize the wasm themselves, with the glue's init
+/// functions, before anything can call into it.
+pub fn wraps(env: Environment) -> bool {
+    env != Environment::Slim
+}
+
+/// Specifier of `env`'s ESM bindings module, relative to its entrypoint.
+pub fn esm_bindings_specifier(env: Environment, variant: WasmVariant) -> String {
+    relative_specifier(&targets::paths::esm_bindings(env, variant))
+}
+
+/// Specifier of `env`'s CJS bindings module, relative to its entrypoint.
+pub fn cjs_bindings_specifier(env: Environment, variant: WasmVariant) -> String {
+    relative_specifier(&targets::paths::cjs_bindings(env, variant))
+}
+
+/// `./{file name}`, for a module next to the one importing it.
+pub fn relative_specifier(path: &Path) -> String {
+    format!("./{}", path.file_name().unwrap().to_string_lossy())
+}
+
+fn bundle(esbuild: &str, source: &Path, format: &str) -> Result<String> {
+    let platform = if format == "cjs" { "node" } else { "neutral" };
+    let output = Command::new(esbuild)
+        .arg(source)
+        .args([
+            "--bundle",
+            "--packages=external",
+            &format!("--external:{BINDINGS_SPECIFIER}"),
+            &format!("--format={format}"),
+            &format!("--platform={platform}"),
+        ])
+        .output()
+        .with_context(|| format!("Failed to run esbuild for {}", source.display()))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "esbuild {format} build of the facade failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    String::from_utf8(output.stdout).context("esbuild output is not UTF-8")
+}
+
+/// Point esbuild's imports of the bindings at `bindings`, leaving any other
+/// occurrence of the specifier (in a string literal, say) alone.
+fn replace_specifier(bundle: &str, bindings: &str) -> String {
+    BINDINGS_IMPORT
+        .replace_all(bundle, |caps: &Captures| {
+            format!("{}\"{bindings}\"", &caps[1])
+        })
+        .into_owned()
+}
+
+/// The first of [`RESERVED_EXPORTS`] the ESM bundle `esm` exports.
+fn reserved_export(esm: &str) -> Option<&'static str> {
+    let exported: Vec<&str> = EXPORT_CLAUSE
+        .captures_iter(esm)
+        .flat_map(|caps| caps.get(1).unwrap().as_str().split(','))
+        .filter_map(|spec| spec.split_whitespace().last())
+        .collect();
+    RESERVED_EXPORTS
+        .into_iter()
+        .find(|name| exported.contains(name))
+}
+
+/// Path of the facade's declarations, relative to the output directory, or
+/// None for a JS facade without `types`.
+pub fn types_path(config: &FacadeConfig) -> Option<PathBuf> {
+    let file_name = declarations::file_name(&config.source, config.types.as_deref())?;
+    Some(targets::paths::facade_types_dir().join(file_name))
+}
+
+/// Declarations of the entrypoints that re-export the facade: the facade's,
+/// or without one (or without its types) the bindings'.
+pub fn entry_types(config: Option<&FacadeConfig>) -> PathBuf {
+    config
+        .and_then(types_path)
+        .unwrap_or_else(targets::paths::types)
+}
+
+/// Write the facade's declarations to `facade-types/`, with its imports of
+/// the bindings pointing at the generated `index.d.ts`, and their per-format
+/// copies. `ready` says whether the bindings' `ready` is re-exported too.
+pub fn write_declarations(
+    config: &FacadeConfig,
+    out_dir: &Path,
+    package_json: &Path,
+    ready: bool,
+) -> Result<()> {
+    let types_dir = out_dir.join(targets::paths::facade_types_dir());
+    let package_types = out_dir.canonicalize()?.join(targets::paths::types());
+    declarations::write(
+        &config.source,
+        config.types.as_deref(),
+        &types_dir,
+        json!({
+            BINDINGS_SPECIFIER: [&package_types],
+            declarations::npm_name(package_json)?: [&package_types],
+        }),
+        "the facade",
+    )?;
+    rewrite_declaration_imports(&types_dir, 1)?;
+    // The facade entrypoints add the bindings' `__wasmBodge` and `ready`
+    if let Some(types) = types_path(config) {
+        let types = out_dir.join(types);
+        let mut content = std::fs::read_to_string(&types)?;
+        content.push_str("export { __wasmBodge } from '../index.js';\n");
+        if ready {
+            content.push_str("export { ready } from '../index.js';\n");
+        }
+        std::fs::write(&types, content)?;
+    }
+    declarations::write_formats(&types_dir)
+}
+
+/// Replace the bindings specifier in the declarations under `dir`, `depth`
+/// directories below the output directory.
+fn rewrite_declaration_imports(dir: &Path, depth: usize) -> Result<()> {
+    let index = format!("{}index.js", "../".repeat(depth));
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            rewrite_declaration_imports(&path, depth + 1)?;
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let rewritten = content
+            .replace(
+                &format!("\"{BINDINGS_SPECIFIER}\""),
+                &format!("\"{index}\""),
+            )
+            .replace(&format!("'{BINDINGS_SPECIFIER}'"), &format!("'{index}'"));
+        if rewritten != content {
+            std::fs::write(&path, rewritten)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_facade_entrypoints() {
+        let facade = Facade {
+            esm: "import { greet } from \"wasm-bodge:bindings\";\nexport * from \"wasm-bodge:bindings\";\n".to_string(),
+            cjs: "var import_bindings = require(\"wasm-bodge:bindings\");\n".to_string(),
+        };
+
+        let specifier = esm_bindings_specifier(Environment::Web, WasmVariant::Debug);
+        assert_eq!(specifier, "./debug-web.bindings.js");
+        assert_eq!(
+            facade.esm_entrypoint(&specifier, true),
+            "import { greet } from \"./debug-web.bindings.js\";\n\
+             export * from \"./debug-web.bindings.js\";\n\
+             export { ready } from './debug-web.bindings.js';\n\
+             export { __wasmBodge } from './debug-web.bindings.js';\n"
+        );
+        assert_eq!(
+            facade.cjs_entrypoint(&cjs_bindings_specifier(
+                Environment::Node,
+                WasmVariant::Optimized
+            )),
+            "var import_bindings = require(\"./node.bindings.cjs\");\n\
+             module.exports.__wasmBodge = require('./node.bindings.cjs').__wasmBodge;\n"
+        );
+    }
+
+    #[test]
+    fn test_facade_specifier_only_in_imports() {
+        let facade = Facade {
+            esm: "import { greet } from \"wasm-bodge:bindings\";\n\
+                  const lazy = () => import(\"wasm-bodge:bindings\");\n\
+                  var name = \"wasm-bodge:bindings\";\n"
+                .to_string(),
+            cjs: "var import_bindings = require(\"wasm-bodge:bindings\");\n\
+                  console.log(\"wasm-bodge:bindings\");\n"
+                .to_string(),
+        };
+        assert_eq!(
+            facade.esm_entrypoint("./web.bindings.js", false),
+            "import { greet } from \"./web.bindings.js\";\n\
+             const lazy = () => import(\"./web.bindings.js\");\n\
+             var name = \"wasm-bodge:bindings\";\n\
+             export { __wasmBodge } from './web.bindings.js';\n"
+        );
+        assert!(facade.cjs_entrypoint("./web.bindings.cjs").starts_with(
+            "var import_bindings = require(\"./web.bindings.cjs\");\n\
+                     console.log(\"wasm-bodge:bindings\");\n"
+        ));
+    }
+
+    #[test]
+    fn test_reserved_exports() {
+        let esm = "import { greet } from \"wasm-bodge:bindings\";\n\
+                   function hello() {}\n\
+                   export {\n  hello,\n  greet as ready\n};\n";
+        assert_eq!(reserved_export(esm), Some("ready"));
+        assert_eq!(
+            reserved_export("export {\n  hello as greet,\n  ready as hello\n};\n"),
+            None
+        );
+    }
+}
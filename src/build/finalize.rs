@@ -1,20 +1,31 @@
 use anyhow::{Context, Result};
 use std::path::Path;
 
-use super::targets::{self, WasmBindgenTarget, WasmVariant};
+use super::declarations;
+use super::targets::{self, ExportMapping, WasmBindgenTarget, WasmVariant};
+use crate::config::{BuildConfig, IifeConfig};
 
 /// Finalize the build by:
 /// 1. Updating package.json with generated exports
-/// 2. Copying .d.ts to out_dir
+/// 2. Writing each export's .d.ts to out_dir, and the IIFE global's
 /// 3. Copying .wasm (per variant) to out_dir
 /// 4. Generating CJS base64 module (per variant)
+/// 5. Building the config file's custom subpath modules
+/// 6. Writing the facade's declarations
+/// 7. Checking the exports' declarations resolve like TypeScript needs
+///
+/// `exports_ready` says whether the entry declarations declare `ready`.
 pub fn run(
-    package_json_path: &Path,
-    out_dir: &Path,
+    config: &BuildConfig,
     crate_name: &str,
     package_name: &str,
     available_variants: &[WasmVariant],
+    export_mapping: &[ExportMapping],
+    exports_ready: bool,
 ) -> Result<()> {
+    let package_json_path = config.package_json.as_path();
+    let out_dir = config.out_dir.as_path();
+    let file_config = &config.file;
     let wasm_name = crate_name.replace('-', "_");
 
     // Compute relative path from package.json directory to out_dir
@@ -35,10 +46,25 @@ pub fn run(
         &out_dir_rel,
         package_name,
         available_variants,
+        export_mapping,
+        file_config,
+        super::package_json::ExportOptions {
+            shared_node_instance: config.shared_node_instance,
+            development_conditions: config.development_conditions,
+        },
     )?;
 
-    // Copy .d.ts from nodejs target to out_dir (shared across variants)
-    copy_types(out_dir, &wasm_name, &out_dir_rel)?;
+    // Declarations for the auto-init, slim, asset-URL and base64 exports
+    let entry_types = super::facade::entry_types(file_config.facade.as_ref());
+    write_types(
+        out_dir,
+        crate_name,
+        &out_dir_rel,
+        available_variants,
+        &entry_types,
+        &file_config.iife,
+        exports_ready,
+    )?;
 
     // Per-variant: copy wasm, generate CJS base64
     for variant in available_variants {
@@ -46,26 +72,153 @@ pub fn run(
         generate_cjs_base64(out_dir, &out_dir_rel, *variant)?;
     }
 
+    super::subpaths::build(&file_config.subpaths, out_dir, package_json_path)?;
+    if let Some(facade_config) = &file_config.facade {
+        super::facade::write_declarations(
+            facade_config,
+            out_dir,
+            package_json_path,
+            exports_ready,
+        )?;
+    }
+
+    let problems = crate::check_types::check(&package_dir)?;
+    if problems.is_empty() {
+        println!("  Checked the exports' types under node10, node16 and bundler resolution");
+    }
+    for problem in problems {
+        eprintln!("  Warning: {problem}");
+    }
+
     Ok(())
 }
 
-fn copy_types(out_dir: &Path, wasm_name: &str, out_dir_rel: &Path) -> Result<()> {
+/// Declaration of the `__wasmBodge` object the auto-initializing entrypoints
+/// export.
+const METADATA_TYPES: &str = r#"/** How this entrypoint was built and initializes the wasm. */
+export declare const __wasmBodge: {
+    readonly variant: "optimized" | "debug";
+    /** The entrypoint's environment, e.g. "node" or "web" */
+    readonly environment: string;
+    /** How the entrypoint initializes the wasm, e.g. "NodeFsSync" */
+    readonly strategy: string;
+    /** The package's name */
+    readonly name: string | null;
+    /** The package's version */
+    readonly version: string | null;
+    /** Hex SHA-256 of the wasm */
+    readonly wasmHash: string;
+    /** Resolves once the wasm is initialized */
+    readonly ready: Promise<void>;
+};
+"#;
+
+/// Declaration of the `ready` promise that async entrypoints export.
+const READY_TYPES: &str = r#"/** Resolves once the wasm is initialized; await it before calling in. */
+export declare const ready: Promise<void>;
+"#;
+
+/// Write the declaration files the exports point at, each with its `.d.mts`
+/// and `.d.cts` copies unless noted:
+/// - index.d.ts, the nodejs target's declarations (just the bindings) plus
+///   `__wasmBodge`, and `ready` if `exports_ready`, for the
+///   auto-initializing entrypoints
+/// - {prefix}slim.d.ts, each variant's web target declarations, which add
+///   `initSync` and the default async `init`
+/// - asset-url.d.ts, the auto-init declarations plus `ready`
+/// - wasm-base64.d.ts
+/// - iife/{index,debug}.d.ts, declaring the IIFE bundles' globals as the
+///   auto-init exports (scripts, so without per-format copies)
+///
+/// Missing wasm-bindgen declarations (e.g. `--no-typescript` output in a
+/// tarball) are skipped along with everything that depends on them.
+fn write_types(
+    out_dir: &Path,
+    crate_name: &str,
+    out_dir_rel: &Path,
+    available_variants: &[WasmVariant],
+    entry_types: &Path,
+    iife: &IifeConfig,
+    exports_ready: bool,
+) -> Result<()> {
+    let wasm_name = crate_name.replace('-', "_");
     let dts_src = out_dir
         .join(targets::paths::wasm_bindgen_dir(WasmBindgenTarget::Nodejs))
         .join(format!("{}.d.ts", wasm_name));
-    let dts_dest = out_dir.join(targets::paths::types());
-
     if dts_src.exists() {
-        std::fs::copy(&dts_src, &dts_dest)?;
+        let bindings = std::fs::read_to_string(&dts_src)?;
+        let ready = if exports_ready { READY_TYPES } else { "" };
+        std::fs::write(
+            out_dir.join(targets::paths::types()),
+            format!("{bindings}\n{METADATA_TYPES}{ready}"),
+        )?;
         println!(
             "  Copied type declarations to {}/{}",
             out_dir_rel.display(),
             targets::paths::types().display()
         );
+
+        std::fs::write(
+            out_dir.join(targets::paths::asset_url_types()),
+            format!(
+                "export * from './{}';\nexport declare const ready: Promise<void>;\n",
+                declared_module(entry_types)
+            ),
+        )?;
+
+        for variant in available_variants {
+            std::fs::write(
+                out_dir.join(targets::paths::iife_types(*variant)),
+                targets::generate_iife_types(
+                    &iife.global_name(crate_name, *variant),
+                    &format!("../{}", declared_module(entry_types)),
+                ),
+            )?;
+        }
+    }
+
+    for variant in available_variants {
+        let web_dts = out_dir
+            .join(format!("wasm_bindgen/web{}", variant.dir_suffix()))
+            .join(format!("{}.d.ts", wasm_name));
+        if web_dts.exists() {
+            std::fs::copy(&web_dts, out_dir.join(targets::paths::slim_types(*variant)))?;
+        }
+    }
+
+    std::fs::write(
+        out_dir.join(targets::paths::wasm_base64_types()),
+        "export declare const wasmBase64: string;\n",
+    )?;
+
+    let mut written = vec![
+        targets::paths::types(),
+        targets::paths::asset_url_types(),
+        targets::paths::wasm_base64_types(),
+    ];
+    written.extend(
+        available_variants
+            .iter()
+            .map(|v| targets::paths::slim_types(*v)),
+    );
+    for types in written {
+        declarations::write_formats(&out_dir.join(types))?;
     }
     Ok(())
 }
 
+/// The JS module a declaration file describes, as imported from a
+/// declaration file: `facade-types/x.d.ts` -> `facade-types/x.js`.
+fn declared_module(types: &Path) -> String {
+    let types = types.display().to_string();
+    for (declaration, module) in [(".d.mts", ".mjs"), (".d.cts", ".cjs"), (".d.ts", ".js")] {
+        if let Some(stem) = types.strip_suffix(declaration) {
+            return format!("{stem}{module}");
+        }
+    }
+    types
+}
+
 fn copy_wasm(
     out_dir: &Path,
     wasm_name: &str,
@@ -0,0 +1,46 @@
+// Base64 decoder for the embedded wasm.
+//
+// `Uint8Array.from(atob(s), c => c.charCodeAt(0))` builds an intermediate
+// string as large as the wasm and runs a callback per byte, which dominates
+// startup for large modules. Prefer the native decoders, then fall back to a
+// lookup-table loop.
+
+const ALPHABET = 'ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/';
+
+export function decodeBase64(base64) {
+    if (typeof Uint8Array.fromBase64 === 'function') {
+        return Uint8Array.fromBase64(base64);
+    }
+    if (typeof Buffer === 'function' && typeof Buffer.from === 'function') {
+        const buf = Buffer.from(base64, 'base64');
+        return new Uint8Array(buf.buffer, buf.byteOffset, buf.byteLength);
+    }
+
+    const lookup = new Uint8Array(128);
+    for (let i = 0; i < ALPHABET.length; i++) lookup[ALPHABET.charCodeAt(i)] = i;
+
+    let len = base64.length;
+    while (len > 0 && base64.charCodeAt(len - 1) === 61 /* = */) len--;
+
+    const out = new Uint8Array((len * 3) >>> 2);
+    let i = 0;
+    let j = 0;
+    for (; i + 4 <= len; i += 4) {
+        const n =
+            (lookup[base64.charCodeAt(i)] << 18) |
+            (lookup[base64.charCodeAt(i + 1)] << 12) |
+            (lookup[base64.charCodeAt(i + 2)] << 6) |
+            lookup[base64.charCodeAt(i + 3)];
+        out[j++] = n >> 16;
+        out[j++] = (n >> 8) & 0xff;
+        out[j++] = n & 0xff;
+    }
+    if (len - i >= 2) {
+        const n = (lookup[base64.charCodeAt(i)] << 18) | (lookup[base64.charCodeAt(i + 1)] << 12);
+        out[j++] = n >> 16;
+        if (len - i === 3) {
+            out[j++] = ((n | (lookup[base64.charCodeAt(i + 2)] << 6)) >> 8) & 0xff;
+        }
+    }
+    return out;
+}
@@ -0,0 +1,174 @@
+// Gzip decoder for the compressed embedded wasm.
+//
+// Entrypoints that initialize the wasm synchronously with `initSync` can't
+// use the async-only `DecompressionStream`, so they decompress with this
+// small DEFLATE implementation instead (adapted from Joergen Ibsen's tinf).
+// The CRC in the gzip trailer is not checked. Async entrypoints use `gunzip`,
+// which prefers the native stream when the runtime has one.
+
+const LENGTH_BASE = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+const CODE_LENGTH_ORDER = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+function buildTree(lengths) {
+    const counts = new Uint16Array(16);
+    for (let i = 0; i < lengths.length; i++) counts[lengths[i]]++;
+    counts[0] = 0;
+    const offsets = new Uint16Array(16);
+    for (let i = 1; i < 16; i++) offsets[i] = offsets[i - 1] + counts[i - 1];
+    const symbols = new Uint16Array(lengths.length);
+    for (let i = 0; i < lengths.length; i++) {
+        if (lengths[i]) symbols[offsets[lengths[i]]++] = i;
+    }
+    return { counts, symbols };
+}
+
+function fixedTrees() {
+    const lengths = new Uint8Array(288);
+    lengths.fill(8, 0, 144);
+    lengths.fill(9, 144, 256);
+    lengths.fill(7, 256, 280);
+    lengths.fill(8, 280, 288);
+    return [buildTree(lengths), buildTree(new Uint8Array(30).fill(5))];
+}
+
+function getBit(s) {
+    if (!s.bitcount--) {
+        s.tag = s.src[s.pos++];
+        s.bitcount = 7;
+    }
+    const bit = s.tag & 1;
+    s.tag >>>= 1;
+    return bit;
+}
+
+function readBits(s, num, base) {
+    if (!num) return base;
+    while (s.bitcount < 24) {
+        s.tag |= (s.src[s.pos++] | 0) << s.bitcount;
+        s.bitcount += 8;
+    }
+    const value = s.tag & (0xffff >>> (16 - num));
+    s.tag >>>= num;
+    s.bitcount -= num;
+    return value + base;
+}
+
+function decodeSymbol(s, tree) {
+    let sum = 0;
+    let cur = 0;
+    let len = 0;
+    do {
+        cur = 2 * cur + getBit(s);
+        len++;
+        sum += tree.counts[len];
+        cur -= tree.counts[len];
+    } while (cur >= 0);
+    return tree.symbols[sum + cur];
+}
+
+function dynamicTrees(s) {
+    const hlit = readBits(s, 5, 257);
+    const hdist = readBits(s, 5, 1);
+    const hclen = readBits(s, 4, 4);
+
+    const codeLengths = new Uint8Array(19);
+    for (let i = 0; i < hclen; i++) codeLengths[CODE_LENGTH_ORDER[i]] = readBits(s, 3, 0);
+    const codeTree = buildTree(codeLengths);
+
+    const lengths = new Uint8Array(hlit + hdist);
+    for (let num = 0; num < hlit + hdist;) {
+        const sym = decodeSymbol(s, codeTree);
+        if (sym < 16) {
+            lengths[num++] = sym;
+            continue;
+        }
+        let prev = 0;
+        let repeat;
+        if (sym === 16) {
+            prev = lengths[num - 1];
+            repeat = readBits(s, 2, 3);
+        } else if (sym === 17) {
+            repeat = readBits(s, 3, 3);
+        } else {
+            repeat = readBits(s, 7, 11);
+        }
+        while (repeat--) lengths[num++] = prev;
+    }
+
+    return [buildTree(lengths.subarray(0, hlit)), buildTree(lengths.subarray(hlit))];
+}
+
+function inflateBlock(s, litTree, distTree) {
+    for (;;) {
+        let sym = decodeSymbol(s, litTree);
+        if (sym === 256) return;
+        if (sym < 256) {
+            s.out[s.outPos++] = sym;
+            continue;
+        }
+        sym -= 257;
+        const length = readBits(s, LENGTH_EXTRA[sym], LENGTH_BASE[sym]);
+        const distSym = decodeSymbol(s, distTree);
+        const from = s.outPos - readBits(s, DIST_EXTRA[distSym], DIST_BASE[distSym]);
+        for (let i = 0; i < length; i++) s.out[s.outPos++] = s.out[from + i];
+    }
+}
+
+function storedBlock(s) {
+    // Give back whole bytes buffered in `tag`; the partial byte is padding
+    while (s.bitcount >= 8) {
+        s.pos--;
+        s.bitcount -= 8;
+    }
+    s.bitcount = 0;
+    s.tag = 0;
+    const length = s.src[s.pos] | (s.src[s.pos + 1] << 8);
+    s.pos += 4;
+    s.out.set(s.src.subarray(s.pos, s.pos + length), s.outPos);
+    s.pos += length;
+    s.outPos += length;
+}
+
+export function gunzipSync(src) {
+    if (src[0] !== 0x1f || src[1] !== 0x8b || src[2] !== 8) {
+        throw new Error('wasm-bodge: embedded wasm is not gzip data');
+    }
+    const flags = src[3];
+    let pos = 10;
+    if (flags & 4) pos += 2 + (src[pos] | (src[pos + 1] << 8));
+    if (flags & 8) while (src[pos++]);
+    if (flags & 16) while (src[pos++]);
+    if (flags & 2) pos += 2;
+
+    const end = src.length;
+    const size = (src[end - 4] | (src[end - 3] << 8) | (src[end - 2] << 16) | (src[end - 1] << 24)) >>> 0;
+    const s = { src, pos, tag: 0, bitcount: 0, out: new Uint8Array(size), outPos: 0 };
+
+    let last;
+    do {
+        last = getBit(s);
+        const type = readBits(s, 2, 0);
+        if (type === 0) {
+            storedBlock(s);
+        } else if (type === 1) {
+            inflateBlock(s, ...fixedTrees());
+        } else if (type === 2) {
+            inflateBlock(s, ...dynamicTrees(s));
+        } else {
+            throw new Error('wasm-bodge: invalid DEFLATE block in embedded wasm');
+        }
+    } while (!last);
+
+    return s.out;
+}
+
+export async function gunzip(src) {
+    if (typeof DecompressionStream === 'function') {
+        const stream = new Blob([src]).stream().pipeThrough(new DecompressionStream('gzip'));
+        return new Uint8Array(await new Response(stream).arrayBuffer());
+    }
+    return gunzipSync(src);
+}
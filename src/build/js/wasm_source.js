@@ -0,0 +1,15 @@
+// Runtime override of the wasm the base64 entrypoints initialize with.
+//
+// A page that sets `globalThis.__wasmBodgeWasm` before importing the package
+// maps the package name (`<name>/debug` for the debug variant) to a URL,
+// Response, precompiled WebAssembly.Module, bytes, or a promise of one. The
+// entrypoint then initializes from that and never imports the base64 wasm.
+
+export function wasmSource(buildInfo) {
+    const sources = globalThis.__wasmBodgeWasm;
+    if (sources == null || buildInfo.name == null) {
+        return undefined;
+    }
+    const key = buildInfo.variant === 'debug' ? `${buildInfo.name}/debug` : buildInfo.name;
+    return sources[key] ?? undefined;
+}
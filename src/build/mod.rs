@@ -5,12 +5,21 @@ use std::process::Command;
 use crate::build::targets::WasmVariant;
 use crate::config::BuildConfig;
 
+mod debug_info;
+pub mod declarations;
+mod dwarf;
 mod entrypoints;
+mod facade;
 mod finalize;
 mod package_json;
 mod post_process;
+mod source_map;
+mod subpaths;
+mod symbols;
 pub mod targets;
+mod wasm_binary;
 mod wasm_bindgen;
+mod wasm_pack;
 
 /// Main build orchestrator
 pub fn run(config: BuildConfig) -> Result<()> {
@@ -18,15 +27,70 @@ pub fn run(config: BuildConfig) -> Result<()> {
 
     let crate_path = &config.crate_path;
 
+    // Check the export condition mapping before spending time on the build
+    let asset_url_conditions: Vec<_> = config
+        .asset_url_conditions
+        .iter()
+        .map(|c| targets::ExportCondition::from(*c))
+        .collect();
+    let export_mapping = match &config.file.conditions {
+        Some(conditions) => targets::configured_export_mapping(conditions)
+            .context("Invalid `conditions` in the config file")?,
+        None => targets::ROOT_EXPORT_MAPPING.to_vec(),
+    };
+    let export_mapping = targets::with_asset_url_conditions(export_mapping, &asset_url_conditions);
+    subpaths::validate(
+        &config.file.subpaths,
+        &package_json::generated_subpaths(&export_mapping),
+    )
+    .context("Invalid `subpaths` in the config file")?;
+    if let Some(facade_config) = &config.file.facade {
+        facade::validate(facade_config).context("Invalid `facade` in the config file")?;
+    }
+    if config.development_conditions && !config.has_debug_variant() {
+        anyhow::bail!(
+            "--development-conditions needs a debug variant; \
+             pass --debug-profile (or --debug-wasm) to build one"
+        );
+    }
+
     // Create output directory
     std::fs::create_dir_all(&config.out_dir).context("Failed to create output directory")?;
 
     let wasm_bindgen_dir = config.out_dir.join("wasm_bindgen");
 
-    // Phase 1: Build wasm or extract from tarball
+    // Get crate name from Cargo.toml, or from the prebuilt wasm / wasm-pack output
+    let crate_name = if let Some(wasm) = &config.wasm {
+        get_prebuilt_wasm_name(wasm, config.wasm_name.as_deref())?
+    } else if let Some(pkg_dir) = &config.wasm_pack_dir {
+        wasm_pack::wasm_name(pkg_dir)?
+    } else {
+        get_crate_name(crate_path)?
+    };
+    println!("Crate name: {}", crate_name);
+
+    // Phase 1: Build wasm, bindgen a prebuilt wasm, import wasm-pack output,
+    // or extract from tarball
     if let Some(tarball) = &config.wasm_bindgen_tar {
         println!("Extracting prebuilt wasm-bindgen output from {:?}", tarball);
         extract_tarball(tarball, &wasm_bindgen_dir)?;
+    } else if let Some(wasm) = &config.wasm {
+        println!(
+            "Phase 1: Running wasm-bindgen on prebuilt wasm {:?}...",
+            wasm
+        );
+        wasm_bindgen::bindgen_prebuilt(
+            wasm,
+            config.debug_wasm.as_deref(),
+            &crate_name.replace('-', "_"),
+            &wasm_bindgen_dir,
+            config.wasm_opt,
+            config.symbol_map.as_deref(),
+        )?;
+    } else if let Some(pkg_dir) = &config.wasm_pack_dir {
+        println!("Phase 1: Importing wasm-pack output from {:?}...", pkg_dir);
+        wasm_pack::import(pkg_dir, &wasm_bindgen_dir, &crate_name)?;
+        wasm_pack::merge_package_json(pkg_dir, &config.package_json)?;
     } else {
         println!("Phase 1: Building wasm...");
         wasm_bindgen::build_wasm(
@@ -35,37 +99,69 @@ pub fn run(config: BuildConfig) -> Result<()> {
             &config.release_profile,
             config.debug_profile.as_deref(),
             config.wasm_opt,
+            config.symbol_map.as_deref(),
         )?;
     }
 
-    // Get crate name from Cargo.toml
-    let crate_name = get_crate_name(crate_path)?;
-    println!("Crate name: {}", crate_name);
-
     // Get package name from package.json (or derive from crate name)
     let package_name = get_package_name(&config.package_json, &crate_name)?;
 
     // Phase 2: Post-process
     println!("Phase 2: Post-processing...");
-    post_process::run(&wasm_bindgen_dir, &config.out_dir, &crate_name)?;
+    // The source map is built from the DWARF, so it has to come before the
+    // DWARF is split out.
+    if config.wasm_source_map {
+        println!("  Generating wasm source map for the debug variant...");
+        source_map::generate(
+            &wasm_bindgen_dir,
+            &crate_name.replace('-', "_"),
+            config.source_map_url.as_deref(),
+            config.source_map_sources_content,
+        )?;
+    }
+    if let Some(debug_dir) = &config.split_debug_info {
+        println!("  Splitting DWARF out of the debug variant...");
+        debug_info::split(
+            &wasm_bindgen_dir,
+            &crate_name.replace('-', "_"),
+            debug_dir,
+            config.debug_info_url.as_deref(),
+        )?;
+    }
+    post_process::run(
+        &wasm_bindgen_dir,
+        &config.out_dir,
+        &crate_name,
+        config.compress_wasm,
+    )?;
 
     // Phase 3: Generate entrypoints
     println!("Phase 3: Generating entrypoints...");
-    entrypoints::generate(&config.out_dir, &crate_name)?;
+    let variant_options = entrypoints::generate(&crate_name, &config)?;
 
     // Phase 4: Finalize package
     println!("Phase 4: Finalizing package...");
-    let available_variants = if config.debug_profile.is_some() {
+    let available_variants = if config.has_debug_variant() {
         WasmVariant::all()
     } else {
         &[WasmVariant::Optimized]
     };
+    // The entry declarations cover every entrypoint the exports map to, and
+    // the IIFE's global, so they declare `ready` if any of them exports it
+    let exports_ready = variant_options.iter().any(|(_, options)| {
+        export_mapping
+            .iter()
+            .flat_map(|m| [m.esm, m.cjs])
+            .chain([targets::Environment::Iife])
+            .any(|env| options.exports_ready(env))
+    });
     finalize::run(
-        &config.package_json,
-        &config.out_dir,
+        &config,
         &crate_name,
         &package_name,
         available_variants,
+        &export_mapping,
+        exports_ready,
     )?;
 
     println!("Build complete! Output in {:?}", config.out_dir);
@@ -102,6 +198,25 @@ fn get_crate_name(crate_path: &Path) -> Result<String> {
         .context("Could not find package name in Cargo.toml")
 }
 
+/// Name used for the wasm-bindgen output of a prebuilt wasm: `--wasm-name`
+/// if given, otherwise the input file's stem.
+fn get_prebuilt_wasm_name(wasm: &Path, wasm_name: Option<&str>) -> Result<String> {
+    if !wasm.exists() {
+        anyhow::bail!("Wasm file not found at {wasm:?}");
+    }
+
+    match wasm_name {
+        Some(name) => Ok(name.to_string()),
+        None => wasm
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(String::from)
+            .with_context(|| {
+                format!("Could not derive a wasm name from {wasm:?}; pass --wasm-name")
+            }),
+    }
+}
+
 fn get_package_name(package_json_path: &Path, crate_name: &str) -> Result<String> {
     let content =
         std::fs::read_to_string(package_json_path).context("Failed to read package.json")?;
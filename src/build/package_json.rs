@@ -2,7 +2,20 @@ use anyhow::{Context, Result};
 use serde_json::{Value, json};
 use std::path::Path;
 
-use super::targets::{self, Environment, ExportCondition, ROOT_EXPORT_MAPPING, WasmVariant};
+use super::declarations::{self, Format};
+use super::targets::{self, Environment, ExportCondition, ExportMapping, WasmVariant};
+use super::{facade, subpaths};
+use crate::config::{FileConfig, SubpathConfig};
+
+/// Build-wide settings that change the exports map.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    /// Node's `import` of `./slim` gets the wrapper over the CJS entrypoint
+    pub shared_node_instance: bool,
+    /// The optimized subpaths pick the debug variant under the
+    /// `development` condition
+    pub development_conditions: bool,
+}
 
 /// Update package.json with generated fields and exports map.
 pub fn update(
@@ -10,9 +23,14 @@ pub fn update(
     out_dir_rel: &Path,
     package_name: &str,
     available_variants: &[WasmVariant],
+    export_mapping: &[ExportMapping],
+    file_config: &FileConfig,
+    options: ExportOptions,
 ) -> Result<()> {
     let dist = out_dir_rel.display().to_string();
     let has_debug = available_variants.contains(&WasmVariant::Debug);
+    // Entrypoints that re-export the facade are typed by its declarations
+    let entry_types = facade::entry_types(file_config.facade.as_ref());
 
     // Read existing package.json
     let package_content =
@@ -44,16 +62,24 @@ pub fn update(
     );
     package_obj.insert(
         "types".to_string(),
-        json!(format!("./{}/{}", dist, targets::paths::types().display())),
+        json!(format!("./{}/{}", dist, entry_types.display())),
     );
 
-    update_side_effects(package_obj, &dist, has_debug)?;
+    update_side_effects(package_obj, &dist, has_debug, file_config.facade.is_some())?;
 
     // Update files array to include out_dir
     update_files_array(package_obj, &dist);
 
     // Generate exports map
-    let exports = build_exports_map(&dist, package_name, has_debug);
+    let exports = build_exports_map(
+        &dist,
+        package_name,
+        has_debug,
+        export_mapping,
+        &file_config.subpaths,
+        &entry_types,
+        options,
+    );
     package_obj.insert("exports".to_string(), exports);
 
     // Write updated package.json
@@ -68,6 +94,7 @@ fn update_side_effects(
     package_obj: &mut serde_json::Map<String, Value>,
     dist: &str,
     has_debug: bool,
+    has_facade: bool,
 ) -> Result<()> {
     let side_effects = package_obj
         .entry("sideEffects")
@@ -80,6 +107,11 @@ fn update_side_effects(
         format!("./{}/esm/node.js", dist),
         format!("./{}/esm/web.js", dist),
         format!("./{}/esm/workerd.js", dist),
+        format!("./{}/esm/deno.js", dist),
+        format!("./{}/esm/bun.js", dist),
+        format!("./{}/esm/edge-light.js", dist),
+        format!("./{}/esm/worker.js", dist),
+        format!("./{}/esm/asset-url.js", dist),
     ];
     if has_debug {
         required_effects.extend([
@@ -87,8 +119,21 @@ fn update_side_effects(
             format!("./{}/esm/debug-node.js", dist),
             format!("./{}/esm/debug-web.js", dist),
             format!("./{}/esm/debug-workerd.js", dist),
+            format!("./{}/esm/debug-deno.js", dist),
+            format!("./{}/esm/debug-bun.js", dist),
+            format!("./{}/esm/debug-edge-light.js", dist),
+            format!("./{}/esm/debug-worker.js", dist),
+            format!("./{}/esm/debug-asset-url.js", dist),
         ]);
     }
+    if has_facade {
+        // The initialization moves into the bindings modules
+        let bindings: Vec<_> = required_effects
+            .iter()
+            .map(|effect| effect.replace(".js", ".bindings.js"))
+            .collect();
+        required_effects.extend(bindings);
+    }
     for effect in required_effects {
         let effect = serde_json::Value::String(effect.to_string());
         if !actual_effects.contains(&effect) {
@@ -119,23 +164,58 @@ fn update_files_array(package_obj: &mut serde_json::Map<String, Value>, dist: &s
     package_obj.insert("files".to_string(), json!(files));
 }
 
-/// Build the exports map for package.json based on the declarative mapping in targets.rs
-fn build_exports_map(dist: &str, package_name: &str, has_debug: bool) -> Value {
+/// Every subpath wasm-bodge generates, including the debug ones, so custom
+/// subpaths don't start colliding when a debug variant is added.
+pub fn generated_subpaths(export_mapping: &[ExportMapping]) -> Vec<String> {
+    match build_exports_map(
+        "dist",
+        "package",
+        true,
+        export_mapping,
+        &[],
+        &targets::paths::types(),
+        ExportOptions::default(),
+    ) {
+        Value::Object(exports) => exports.keys().cloned().collect(),
+        _ => unreachable!("exports map is an object"),
+    }
+}
+
+/// Build the exports map for package.json from `export_mapping` (the
+/// declarative mapping in targets.rs, or the config file's), followed by the
+/// config file's custom subpaths
+fn build_exports_map(
+    dist: &str,
+    package_name: &str,
+    has_debug: bool,
+    export_mapping: &[ExportMapping],
+    custom_subpaths: &[SubpathConfig],
+    entry_types: &Path,
+    options: ExportOptions,
+) -> Value {
     let p = |path: &Path| format!("./{}/{}", dist, path.display());
 
     let mut exports = serde_json::Map::new();
 
-    // Root "." + ./slim + ./wasm + ./wasm-base64 + ./iife use optimized variant
+    // Root "." + ./slim + ./asset-url + ./wasm + ./wasm-base64 + ./iife use
+    // optimized variant
     exports.insert(
         ".".to_string(),
-        build_conditional_export(dist, WasmVariant::Optimized),
+        build_conditional_export(dist, WasmVariant::Optimized, export_mapping, entry_types),
     );
     exports.insert(
         "./slim".to_string(),
+        slim_export(dist, WasmVariant::Optimized, options.shared_node_instance),
+    );
+    exports.insert(
+        "./asset-url".to_string(),
         json!({
-            "types": p(&targets::paths::types()),
-            "import": p(&targets::paths::esm_entrypoint(Environment::Slim, WasmVariant::Optimized)),
-            "require": p(&targets::paths::cjs_entrypoint(Environment::Slim, WasmVariant::Optimized))
+            "import": typed(
+                dist,
+                Some(&targets::paths::asset_url_types()),
+                Format::Esm,
+                &targets::paths::esm_entrypoint(Environment::AssetUrl, WasmVariant::Optimized),
+            )
         }),
     );
     exports.insert(
@@ -148,8 +228,18 @@ fn build_exports_map(dist: &str, package_name: &str, has_debug: bool) -> Value {
     exports.insert(
         "./wasm-base64".to_string(),
         json!({
-            "import": p(&targets::paths::wasm_base64_esm(WasmVariant::Optimized)),
-            "require": p(&targets::paths::wasm_base64_cjs(WasmVariant::Optimized))
+            "import": typed(
+                dist,
+                Some(&targets::paths::wasm_base64_types()),
+                Format::Esm,
+                &targets::paths::wasm_base64_esm(WasmVariant::Optimized),
+            ),
+            "require": typed(
+                dist,
+                Some(&targets::paths::wasm_base64_types()),
+                Format::Cjs,
+                &targets::paths::wasm_base64_cjs(WasmVariant::Optimized),
+            )
         }),
     );
     exports.insert(
@@ -157,22 +247,29 @@ fn build_exports_map(dist: &str, package_name: &str, has_debug: bool) -> Value {
         json!(p(&targets::paths::iife_bundle(WasmVariant::Optimized))),
     );
 
-    // Debug variant exports mirror the optimized side: ./, ./slim, ./wasm,
-    // ./wasm-base64, ./iife. ./debug/slim exists because the debug wasm has
+    // Debug variant exports mirror the optimized side: ./, ./slim,
+    // ./asset-url, ./wasm, ./wasm-base64, ./iife. ./debug/slim exists because the debug wasm has
     // different imports than the optimized wasm (e.g. __wbindgen_throw is
     // optimized away in release), so the JS bindings paired with each variant
     // are not interchangeable.
     if has_debug {
         exports.insert(
             "./debug".to_string(),
-            build_conditional_export(dist, WasmVariant::Debug),
+            build_conditional_export(dist, WasmVariant::Debug, export_mapping, entry_types),
         );
         exports.insert(
             "./debug/slim".to_string(),
+            slim_export(dist, WasmVariant::Debug, options.shared_node_instance),
+        );
+        exports.insert(
+            "./debug/asset-url".to_string(),
             json!({
-                "types": p(&targets::paths::types()),
-                "import": p(&targets::paths::esm_entrypoint(Environment::Slim, WasmVariant::Debug)),
-                "require": p(&targets::paths::cjs_entrypoint(Environment::Slim, WasmVariant::Debug))
+                "import": typed(
+                    dist,
+                    Some(&targets::paths::asset_url_types()),
+                    Format::Esm,
+                    &targets::paths::esm_entrypoint(Environment::AssetUrl, WasmVariant::Debug),
+                )
             }),
         );
         exports.insert(
@@ -185,45 +282,109 @@ fn build_exports_map(dist: &str, package_name: &str, has_debug: bool) -> Value {
         exports.insert(
             "./debug/wasm-base64".to_string(),
             json!({
-                "import": p(&targets::paths::wasm_base64_esm(WasmVariant::Debug)),
-                "require": p(&targets::paths::wasm_base64_cjs(WasmVariant::Debug))
+                "import": typed(
+                    dist,
+                    Some(&targets::paths::wasm_base64_types()),
+                    Format::Esm,
+                    &targets::paths::wasm_base64_esm(WasmVariant::Debug),
+                ),
+                "require": typed(
+                    dist,
+                    Some(&targets::paths::wasm_base64_types()),
+                    Format::Cjs,
+                    &targets::paths::wasm_base64_cjs(WasmVariant::Debug),
+                )
             }),
         );
         exports.insert(
             "./debug/iife".to_string(),
             json!(p(&targets::paths::iife_bundle(WasmVariant::Debug))),
         );
+
+        // Bundlers' dev servers resolve `development`, so they get the debug
+        // variant of everything that has one, slim and its wasm included so
+        // they still share the glue with `.`
+        if options.development_conditions {
+            let subpaths: Vec<String> = exports
+                .keys()
+                .filter(|subpath| *subpath != "./debug" && !subpath.starts_with("./debug/"))
+                .cloned()
+                .collect();
+            for subpath in subpaths {
+                let debug = match subpath.strip_prefix("./") {
+                    Some(name) => format!("./debug/{name}"),
+                    None => "./debug".to_string(),
+                };
+                let optimized = exports.remove(&subpath).unwrap();
+                let merged = by_mode(optimized, exports[&debug].clone());
+                exports.insert(subpath, merged);
+            }
+        }
+    }
+
+    for subpath in custom_subpaths {
+        let name = subpaths::name(subpath);
+        let types = subpaths::types_path(subpath);
+        exports.insert(
+            subpath.path.clone(),
+            json!({
+                "import": typed(
+                    dist,
+                    types.as_deref(),
+                    Format::Esm,
+                    &targets::paths::subpath_esm(name),
+                ),
+                "require": typed(
+                    dist,
+                    types.as_deref(),
+                    Format::Cjs,
+                    &targets::paths::subpath_cjs(name),
+                )
+            }),
+        );
     }
 
     Value::Object(exports)
 }
 
 /// Build the conditional export object for either `.` or `./debug`. Has
-/// identical shape (types + conditions), differing only in which variant's
-/// entrypoint files it points at.
-fn build_conditional_export(dist: &str, variant: WasmVariant) -> Value {
-    let p = |path: &Path| format!("./{}/{}", dist, path.display());
-
+/// identical shape (conditions, each with per-format types), differing only
+/// in which variant's entrypoint files it points at.
+fn build_conditional_export(
+    dist: &str,
+    variant: WasmVariant,
+    export_mapping: &[ExportMapping],
+    entry_types: &Path,
+) -> Value {
     let mut root_export = serde_json::Map::new();
-    root_export.insert("types".to_string(), json!(p(&targets::paths::types())));
 
-    for mapping in ROOT_EXPORT_MAPPING {
-        let esm_path = p(&targets::paths::esm_entrypoint(mapping.esm, variant));
-        let cjs_path = p(&targets::paths::cjs_entrypoint(mapping.cjs, variant));
+    for mapping in export_mapping {
+        let esm = typed(
+            dist,
+            Some(entry_types),
+            Format::Esm,
+            &targets::paths::esm_entrypoint(mapping.esm, variant),
+        );
+        let cjs = typed(
+            dist,
+            Some(entry_types),
+            Format::Cjs,
+            &targets::paths::cjs_entrypoint(mapping.cjs, variant),
+        );
 
         match mapping.condition {
             ExportCondition::Import => {
-                root_export.insert("import".to_string(), json!(esm_path));
+                root_export.insert("import".to_string(), esm);
             }
             ExportCondition::Require => {
-                root_export.insert("require".to_string(), json!(cjs_path));
+                root_export.insert("require".to_string(), cjs);
             }
             _ => {
                 root_export.insert(
                     mapping.condition.as_str().to_string(),
                     json!({
-                        "import": esm_path,
-                        "require": cjs_path
+                        "import": esm,
+                        "require": cjs
                     }),
                 );
             }
@@ -232,3 +393,196 @@ fn build_conditional_export(dist: &str, variant: WasmVariant) -> Value {
 
     Value::Object(root_export)
 }
+
+/// Merge an optimized export with its debug counterpart of the same shape:
+/// each target becomes `{ "development", "production", "default" }`, with
+/// the debug variant's target under `development` and the optimized one
+/// otherwise.
+fn by_mode(optimized: Value, debug: Value) -> Value {
+    match (optimized, debug) {
+        (Value::Object(optimized), Value::Object(mut debug))
+            if !optimized.contains_key("default") =>
+        {
+            let merged = optimized
+                .into_iter()
+                .map(|(condition, target)| {
+                    let target = match debug.remove(&condition) {
+                        Some(debug) => by_mode(target, debug),
+                        None => target,
+                    };
+                    (condition, target)
+                })
+                .collect();
+            Value::Object(merged)
+        }
+        (optimized, debug) => json!({
+            "development": debug,
+            "production": optimized,
+            "default": optimized
+        }),
+    }
+}
+
+/// The export for `./slim` or `./debug/slim`. With a shared Node instance,
+/// Node's `import` gets the ESM wrapper over the CJS entrypoint, so the
+/// manual-init bindings are the ones the Node entrypoints initialize.
+fn slim_export(dist: &str, variant: WasmVariant, shared_node_instance: bool) -> Value {
+    let types = targets::paths::slim_types(variant);
+    let cjs = typed(
+        dist,
+        Some(&types),
+        Format::Cjs,
+        &targets::paths::cjs_entrypoint(Environment::Slim, variant),
+    );
+    let mut export = serde_json::Map::new();
+    if shared_node_instance {
+        export.insert(
+            "node".to_string(),
+            json!({
+                "import": typed(
+                    dist,
+                    Some(&types),
+                    Format::Esm,
+                    &targets::paths::esm_node_slim(variant),
+                ),
+                "require": cjs
+            }),
+        );
+    }
+    export.insert(
+        "import".to_string(),
+        typed(
+            dist,
+            Some(&types),
+            Format::Esm,
+            &targets::paths::esm_entrypoint(Environment::Slim, variant),
+        ),
+    );
+    export.insert("require".to_string(), cjs);
+    Value::Object(export)
+}
+
+/// An `import` or `require` target: `{ "types", "default" }` with the
+/// declarations of `types` for `format`, so TypeScript reads them as the
+/// same format as the JS, or just the JS path without `types`.
+fn typed(dist: &str, types: Option<&Path>, format: Format, target: &Path) -> Value {
+    let p = |path: &Path| format!("./{}/{}", dist, path.display());
+    match types {
+        Some(types) => json!({
+            "types": p(&declarations::format_path(types, format)),
+            "default": p(target)
+        }),
+        None => json!(p(target)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The generated exports, with every file they point at in place, have
+    /// no problems under any moduleResolution mode.
+    #[test]
+    fn test_exports_pass_type_checks() {
+        let dir = std::env::temp_dir().join("wasm-bodge-test-exports");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let exports = build_exports_map(
+            "dist",
+            "test-lib",
+            true,
+            targets::ROOT_EXPORT_MAPPING,
+            &[],
+            &targets::paths::types(),
+            ExportOptions {
+                shared_node_instance: true,
+                development_conditions: true,
+            },
+        );
+
+        let mut files = vec![Value::String("./dist/cjs/node.cjs".to_string())];
+        let mut targets = vec![&exports];
+        while let Some(target) = targets.pop() {
+            match target {
+                Value::Object(map) => targets.extend(map.values()),
+                path => files.push(path.clone()),
+            }
+        }
+        for file in files {
+            let path = dir.join(file.as_str().unwrap());
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        std::fs::write(
+            dir.join("dist/index.d.ts"),
+            "export declare function greet(): void;\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("package.json"),
+            json!({
+                "type": "module",
+                "main": "./dist/cjs/node.cjs",
+                "types": "./dist/index.d.ts",
+                "exports": exports
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let problems = crate::check_types::check(&dir).unwrap();
+        assert!(problems.is_empty(), "{problems:#?}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_development_conditions() {
+        let exports = build_exports_map(
+            "dist",
+            "test-lib",
+            true,
+            targets::ROOT_EXPORT_MAPPING,
+            &[],
+            &targets::paths::types(),
+            ExportOptions {
+                shared_node_instance: false,
+                development_conditions: true,
+            },
+        );
+
+        assert_eq!(
+            exports["."]["node"]["require"],
+            json!({
+                "development": {
+                    "types": "./dist/index.d.cts",
+                    "default": "./dist/cjs/debug-node.cjs"
+                },
+                "production": {
+                    "types": "./dist/index.d.cts",
+                    "default": "./dist/cjs/node.cjs"
+                },
+                "default": {
+                    "types": "./dist/index.d.cts",
+                    "default": "./dist/cjs/node.cjs"
+                }
+            })
+        );
+        assert_eq!(
+            exports["./slim"]["import"]["development"]["types"],
+            "./dist/debug-slim.d.mts"
+        );
+        assert_eq!(
+            exports["./wasm"],
+            json!({
+                "development": "./dist/test-lib-debug.wasm",
+                "production": "./dist/test-lib.wasm",
+                "default": "./dist/test-lib.wasm"
+            })
+        );
+        // The explicit debug subpaths stay as they were
+        assert_eq!(
+            exports["./debug"]["node"]["require"]["default"],
+            "./dist/cjs/debug-node.cjs"
+        );
+    }
+}
@@ -1,16 +1,24 @@
 use anyhow::{Context, Result};
 use base64::Engine;
 use regex::Regex;
+use std::io::Write;
 use std::path::Path;
 
 use super::targets::{self, WasmBindgenTarget, WasmVariant};
+use crate::config::WasmCompression;
 
 /// Post-process wasm-bindgen output:
 /// 1. Rename nodejs output .js to .cjs (since package uses "type": "module")
 /// 2. For each variant's web target: apply @vite-ignore fix and add
 ///    `__wbg_set_wasm` export
-/// 3. Generate a base64 wasm module for each variant
-pub fn run(wasm_bindgen_dir: &Path, out_dir: &Path, crate_name: &str) -> Result<()> {
+/// 3. Generate a base64 wasm module for each variant, plus a compressed one
+///    when `compression` is set
+pub fn run(
+    wasm_bindgen_dir: &Path,
+    out_dir: &Path,
+    crate_name: &str,
+    compression: Option<WasmCompression>,
+) -> Result<()> {
     // Normalize crate name (Rust uses underscores in generated files)
     let wasm_name = crate_name.replace('-', "_");
 
@@ -46,6 +54,16 @@ pub fn run(wasm_bindgen_dir: &Path, out_dir: &Path, crate_name: &str) -> Result<
             }
         );
         generate_base64_module(&web_dir, out_dir, &wasm_name, *variant)?;
+
+        if let Some(compression) = compression {
+            generate_compressed_base64_module(
+                &web_dir,
+                out_dir,
+                &wasm_name,
+                *variant,
+                compression,
+            )?;
+        }
     }
 
     Ok(())
@@ -56,33 +74,83 @@ fn apply_vite_fix(web_dir: &Path, wasm_name: &str) -> Result<()> {
     let content =
         std::fs::read_to_string(&js_file).context("Failed to read wasm-bindgen JS file")?;
 
-    // Replace: new URL('{name}_bg.wasm', import.meta.url)
-    // With:    new /* @vite-ignore */ URL('{name}_bg.wasm', import.meta.url)
-    let pattern = format!(r"new URL\('{}_bg\.wasm', import\.meta\.url\)", wasm_name);
-    let replacement = format!(
-        "new /* @vite-ignore */ URL('{}_bg.wasm', import.meta.url)",
-        wasm_name
-    );
-
-    let re = Regex::new(&pattern)?;
-    let new_content = re.replace_all(&content, replacement.as_str());
+    let new_content = vite_fix(&content, wasm_name)
+        .with_context(|| format!("Failed to patch {}", js_file.display()))?;
 
-    std::fs::write(&js_file, new_content.as_ref()).context("Failed to write modified JS file")?;
+    std::fs::write(&js_file, new_content).context("Failed to write modified JS file")?;
 
     Ok(())
 }
 
+/// Insert `/* @vite-ignore */` into the default wasm URL expression:
+///
+/// `new URL('{name}_bg.wasm', import.meta.url)` becomes
+/// `new /* @vite-ignore */ URL('{name}_bg.wasm', import.meta.url)`
+///
+/// Tolerates either quote style, a leading `./` and any whitespace, since
+/// wasm-bindgen has changed its formatting between releases. Fails if no
+/// such expression exists, because a silently unpatched module makes Vite
+/// bundle the wasm a second time.
+fn vite_fix(content: &str, wasm_name: &str) -> Result<String> {
+    let pattern = format!(
+        r#"new\s+URL\(\s*['"`](?:\./)?{}_bg\.wasm['"`]\s*,\s*import\.meta\.url\s*\)"#,
+        regex::escape(wasm_name)
+    );
+    let re = Regex::new(&pattern)?;
+    if !re.is_match(content) {
+        anyhow::bail!(
+            "could not find `new URL('{wasm_name}_bg.wasm', import.meta.url)` in the \
+             wasm-bindgen web output to apply the @vite-ignore fix. This wasm-bindgen \
+             version may not be supported yet."
+        );
+    }
+
+    Ok(re
+        .replace_all(content, |caps: &regex::Captures| {
+            caps[0].replacen("new", "new /* @vite-ignore */", 1)
+        })
+        .into_owned())
+}
+
 fn add_set_wasm_export(web_dir: &Path, wasm_name: &str) -> Result<()> {
     let js_file = web_dir.join(format!("{}.js", wasm_name));
-    let mut content =
+    let content =
         std::fs::read_to_string(&js_file).context("Failed to read wasm-bindgen web JS file")?;
 
-    content.push_str("\nexport function __wbg_set_wasm(val) { wasm = val; }\n");
+    let new_content = set_wasm_export(&content)
+        .with_context(|| format!("Failed to patch {}", js_file.display()))?;
 
-    std::fs::write(&js_file, &content).context("Failed to write modified web JS file")?;
+    std::fs::write(&js_file, new_content).context("Failed to write modified web JS file")?;
     Ok(())
 }
 
+/// Append `export function __wbg_set_wasm(val) { wasm = val; }`.
+///
+/// The function assigns the module-level `wasm` binding that every binding
+/// function reads, so we check that binding is actually declared (e.g.
+/// `let wasm;` or `let wasmModule, wasmInstance, wasm;`) instead of emitting
+/// an assignment to an undeclared variable, which would throw in strict mode
+/// the first time the bundler shim runs.
+fn set_wasm_export(content: &str) -> Result<String> {
+    // Web glue generated from wasm-pack bundler output already has it
+    if content.contains("export function __wbg_set_wasm(") {
+        return Ok(content.to_string());
+    }
+
+    let declaration = Regex::new(r"(?m)^(?:let|var)\s+(?:[\w$]+\s*,\s*)*wasm\s*[,;=]")?;
+    if !declaration.is_match(content) {
+        anyhow::bail!(
+            "could not find a module-level `let wasm` declaration in the wasm-bindgen web \
+             output to add the __wbg_set_wasm export. This wasm-bindgen version may not \
+             be supported yet."
+        );
+    }
+
+    Ok(format!(
+        "{content}\nexport function __wbg_set_wasm(val) {{ wasm = val; }}\n"
+    ))
+}
+
 fn generate_base64_module(
     web_dir: &Path,
     out_dir: &Path,
@@ -105,3 +173,112 @@ fn generate_base64_module(
 
     Ok(())
 }
+
+/// Write the compressed, base64-encoded wasm for the base64-embedded
+/// entrypoints and report how much it saves.
+fn generate_compressed_base64_module(
+    web_dir: &Path,
+    out_dir: &Path,
+    wasm_name: &str,
+    variant: WasmVariant,
+    compression: WasmCompression,
+) -> Result<()> {
+    let wasm_file = web_dir.join(format!("{}_bg.wasm", wasm_name));
+    let wasm_bytes = std::fs::read(&wasm_file).context("Failed to read wasm file")?;
+
+    let compressed = match compression {
+        WasmCompression::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(&wasm_bytes)?;
+            encoder.finish().context("Failed to gzip wasm")?
+        }
+    };
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let base64_string = engine.encode(&compressed);
+    let esm_content = format!("export const wasmBase64Gzip = \"{}\";\n", base64_string);
+    std::fs::write(
+        out_dir.join(targets::paths::wasm_base64_gzip_esm(variant)),
+        esm_content,
+    )?;
+
+    println!(
+        "  Compressed {} wasm with gzip: {} -> {} bytes ({:.0}%), embedded base64 {} -> {} bytes",
+        if variant.is_debug() {
+            "debug"
+        } else {
+            "optimized"
+        },
+        wasm_bytes.len(),
+        compressed.len(),
+        100.0 * compressed.len() as f64 / wasm_bytes.len().max(1) as f64,
+        base64::encoded_len(wasm_bytes.len(), true).unwrap_or(0),
+        base64_string.len(),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Web target output of the fixture crate, one directory per pinned
+    /// wasm-bindgen version. Add a directory here when supporting a new
+    /// wasm-bindgen release.
+    fn pinned_web_outputs() -> Vec<(String, String)> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/wasm-bindgen-web");
+        let mut outputs: Vec<_> = std::fs::read_dir(&root)
+            .unwrap()
+            .map(|entry| {
+                let dir = entry.unwrap().path();
+                let version = dir.file_name().unwrap().to_string_lossy().into_owned();
+                let js = std::fs::read_to_string(dir.join("test_wasm_lib.js")).unwrap();
+                (version, js)
+            })
+            .collect();
+        outputs.sort();
+        assert!(
+            outputs.len() > 1,
+            "expected several pinned wasm-bindgen versions"
+        );
+        outputs
+    }
+
+    #[test]
+    fn test_patches_apply_to_pinned_wasm_bindgen_versions() {
+        for (version, js) in pinned_web_outputs() {
+            let patched = vite_fix(&js, "test_wasm_lib")
+                .unwrap_or_else(|e| panic!("vite fix failed for wasm-bindgen {version}: {e}"));
+            assert!(
+                patched.contains("new /* @vite-ignore */ URL('test_wasm_lib_bg.wasm'"),
+                "wasm-bindgen {version}: @vite-ignore not inserted"
+            );
+
+            let patched = set_wasm_export(&patched).unwrap_or_else(|e| {
+                panic!("set_wasm export failed for wasm-bindgen {version}: {e}")
+            });
+            assert!(patched.ends_with("export function __wbg_set_wasm(val) { wasm = val; }\n"));
+        }
+    }
+
+    #[test]
+    fn test_vite_fix_tolerates_formatting_changes() {
+        let js = "input = new URL(\"./my_lib_bg.wasm\",  import.meta.url );";
+        assert_eq!(
+            vite_fix(js, "my_lib").unwrap(),
+            "input = new /* @vite-ignore */ URL(\"./my_lib_bg.wasm\",  import.meta.url );"
+        );
+    }
+
+    #[test]
+    fn test_missing_patch_sites_fail_loudly() {
+        let js = "module_or_path = fetch('my_lib_bg.wasm');\nlet wasm_bindgen;\n";
+        let err = vite_fix(js, "my_lib").unwrap_err();
+        assert!(err.to_string().contains("@vite-ignore"), "{err}");
+
+        let err = set_wasm_export(js).unwrap_err();
+        assert!(err.to_string().contains("let wasm"), "{err}");
+    }
+}
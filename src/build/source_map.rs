@@ -0,0 +1,284 @@
+//! Wasm source maps generated from the debug variant's DWARF.
+//!
+//! Only Chrome (with an extension) can use DWARF directly. Firefox and
+//! Safari devtools instead read a `sourceMappingURL` custom section pointing
+//! at a standard source map, where every mapping sits on line 0 and the
+//! generated column is the byte offset in the wasm file.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::json;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use super::dwarf::{self, LineEntry};
+use super::targets::{WasmBindgenTarget, WasmVariant};
+use super::wasm_binary::{self, Section};
+
+/// Name of the custom section that points at the source map.
+const SOURCE_MAPPING_URL: &str = "sourceMappingURL";
+
+/// Write `{wasm_name}_bg.wasm.map` next to the debug variant's web and
+/// bundler wasm, and add a `sourceMappingURL` section to each wasm.
+///
+/// `url` defaults to the map's file name, which devtools resolve relative to
+/// the wasm's URL. With `sources_content`, source files that exist on this
+/// machine are embedded in the map.
+pub fn generate(
+    wasm_bindgen_dir: &Path,
+    wasm_name: &str,
+    url: Option<&str>,
+    sources_content: bool,
+) -> Result<()> {
+    let map_name = format!("{wasm_name}_bg.wasm.map");
+    let url = url.unwrap_or(&map_name);
+
+    let mut generated = false;
+    for target in [WasmBindgenTarget::Web, WasmBindgenTarget::Bundler] {
+        let dir = wasm_bindgen_dir.join(format!(
+            "{}{}",
+            target.dir_name(),
+            WasmVariant::Debug.dir_suffix()
+        ));
+        let wasm_path = dir.join(format!("{wasm_name}_bg.wasm"));
+        if !wasm_path.exists() {
+            continue;
+        }
+
+        let bytes = std::fs::read(&wasm_path).context("Failed to read debug wasm")?;
+        let sections = wasm_binary::sections(&bytes)?;
+        let table = dwarf::line_table(&sections)
+            .with_context(|| format!("Failed to read DWARF from {}", wasm_path.display()))?;
+        if table.entries.is_empty() {
+            anyhow::bail!(
+                "The debug wasm has no DWARF line info to build a source map from. Build \
+                 the debug variant with `debug = \"full\"` in its Cargo profile."
+            );
+        }
+
+        // Take the code offset from the re-encoded module: re-encoding can
+        // shorten padded section sizes ahead of the code section.
+        let output = with_source_mapping_url(&sections, url);
+        let output_sections = wasm_binary::sections(&output)?;
+        let code = output_sections
+            .iter()
+            .find(|s| s.id == wasm_binary::CODE_SECTION)
+            .context("Debug wasm has no code section")?;
+
+        let map = source_map(
+            &table.files,
+            &table.entries,
+            code.payload_offset(&output) as u64,
+            sources_content,
+        );
+        std::fs::write(dir.join(&map_name), serde_json::to_string(&map)?)?;
+        std::fs::write(&wasm_path, &output)?;
+        println!(
+            "  Wrote source map {} ({} line table rows, {} sources)",
+            dir.join(&map_name).display(),
+            table.entries.len(),
+            table.files.len()
+        );
+        generated = true;
+    }
+
+    if !generated {
+        anyhow::bail!(
+            "--wasm-source-map needs a debug variant. Pass --debug-profile or --debug-wasm."
+        );
+    }
+    Ok(())
+}
+
+fn source_map(
+    files: &[String],
+    entries: &[LineEntry],
+    code_offset: u64,
+    sources_content: bool,
+) -> serde_json::Value {
+    let mut map = json!({
+        "version": 3,
+        "sources": files,
+        "names": [],
+        "mappings": encode_mappings(entries, code_offset),
+    });
+    if sources_content {
+        let contents: Vec<Option<String>> = files
+            .iter()
+            .map(|f| std::fs::read_to_string(f).ok())
+            .collect();
+        map["sourcesContent"] = json!(contents);
+    }
+    map
+}
+
+/// Encode `entries` as the single line of a source map's `mappings`, with
+/// each address shifted by `code_offset` to make it a file offset.
+/// Consecutive entries at the same source position are merged.
+fn encode_mappings(entries: &[LineEntry], code_offset: u64) -> String {
+    let mut out = String::new();
+    let mut prev = [0i64; 4];
+    let mut last: Option<&LineEntry> = None;
+    for entry in entries {
+        if last
+            .is_some_and(|l| (l.file, l.line, l.column) == (entry.file, entry.line, entry.column))
+        {
+            continue;
+        }
+        last = Some(entry);
+
+        let fields = [
+            (entry.address + code_offset) as i64,
+            entry.file as i64,
+            entry.line as i64 - 1,
+            (entry.column as i64 - 1).max(0),
+        ];
+        if !out.is_empty() {
+            out.push(',');
+        }
+        for (field, prev) in fields.iter().zip(prev.iter_mut()) {
+            encode_vlq(&mut out, field - *prev);
+            *prev = *field;
+        }
+    }
+    out
+}
+
+/// Append `value` as a base64 VLQ.
+fn encode_vlq(out: &mut String, value: i64) {
+    const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = vlq & 0x1f;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0x20;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            return;
+        }
+    }
+}
+
+/// wasm-bindgen glue imported by a generated entrypoint.
+static GLUE_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"'(\.\./wasm_bindgen/[^']+\.js)'").unwrap());
+
+/// Write a generated JS entrypoint to `path` with a linked `<path>.map`.
+///
+/// The entrypoint is hand-generated, so the map is an identity map over its
+/// own lines. The wasm-bindgen glue it imports is listed alongside with its
+/// contents, so devtools show it next to the entrypoint.
+pub fn write_js_identity_mapped(path: &Path, content: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .context("Entrypoint has no file name")?
+        .to_string_lossy()
+        .into_owned();
+    let dir = path
+        .parent()
+        .context("Entrypoint has no parent directory")?;
+
+    let mut sources = vec![file_name.clone()];
+    let mut contents = vec![Some(content.to_string())];
+    for glue in GLUE_IMPORT.captures_iter(content) {
+        let glue = &glue[1];
+        if !sources.iter().any(|s| s == glue) {
+            sources.push(glue.to_string());
+            contents.push(std::fs::read_to_string(dir.join(glue)).ok());
+        }
+    }
+
+    let map = json!({
+        "version": 3,
+        "file": file_name,
+        "sources": sources,
+        "sourcesContent": contents,
+        "names": [],
+        "mappings": identity_mappings(content),
+    });
+    let map_name = format!("{file_name}.map");
+    std::fs::write(dir.join(&map_name), serde_json::to_string(&map)?)?;
+    std::fs::write(path, format!("{content}//# sourceMappingURL={map_name}\n"))?;
+    Ok(())
+}
+
+/// Map every non-empty line of `content` to the same line of source 0.
+fn identity_mappings(content: &str) -> String {
+    let mut out = String::new();
+    let mut prev_line = 0;
+    for (line, text) in content.lines().enumerate() {
+        if line > 0 {
+            out.push(';');
+        }
+        if !text.is_empty() {
+            out.push_str("AA");
+            encode_vlq(&mut out, line as i64 - prev_line);
+            out.push('A');
+            prev_line = line as i64;
+        }
+    }
+    out
+}
+
+/// Re-encode the module with a `sourceMappingURL` section pointing at `url`,
+/// replacing any existing one.
+fn with_source_mapping_url(sections: &[Section<'_>], url: &str) -> Vec<u8> {
+    let mut url_data = Vec::new();
+    wasm_binary::write_name(&mut url_data, url);
+    let payload = wasm_binary::custom_section_payload(SOURCE_MAPPING_URL, &url_data);
+
+    let kept = sections
+        .iter()
+        .filter(|s| s.custom_name() != Some(SOURCE_MAPPING_URL))
+        .copied();
+    wasm_binary::encode(kept.chain([Section {
+        id: 0,
+        payload: &payload,
+    }]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vlq() {
+        let mut out = String::new();
+        for value in [0, 1, -1, 15, 16, -16, 1000] {
+            encode_vlq(&mut out, value);
+            out.push(' ');
+        }
+        assert_eq!(out, "A C D e gB hB w+B ");
+    }
+
+    #[test]
+    fn test_mappings_are_file_offsets_and_zero_based() {
+        let entry = |address, file, line, column| LineEntry {
+            address,
+            file,
+            line,
+            column,
+        };
+        let entries = [
+            entry(10, 0, 1, 1),
+            // Same position as the previous row: merged
+            entry(12, 0, 1, 1),
+            entry(20, 1, 5, 0),
+            entry(30, 0, 3, 4),
+        ];
+        // Offsets 110, 120, 130; files 0, 1, 0; lines 0, 4, 2; columns 0, 0, 3
+        assert_eq!(encode_mappings(&entries, 100), "8GAAA,UCIA,UDFG");
+    }
+
+    #[test]
+    fn test_identity_mappings_skip_empty_lines() {
+        // Lines 0, 1 and 3 map to themselves
+        assert_eq!(identity_mappings("a\nb\n\nc\n"), "AAAA;AACA;;AAEA");
+    }
+}
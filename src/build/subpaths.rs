@@ -0,0 +1,156 @@
+//! Custom subpath exports for hand-written JS/TS modules, declared as
+//! `[[subpaths]]` in the config file.
+//!
+//! Each source is bundled by esbuild into an ESM and a CJS module. Bare
+//! imports stay external, including the package's own name, so a wrapper that
+//! imports the bindings from the package gets whichever entrypoint the
+//! consumer's environment resolved. TypeScript sources get declarations from
+//! tsc, unless hand-written `types` are given.
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::declarations;
+use super::entrypoints::find_esbuild;
+use super::targets;
+use crate::config::SubpathConfig;
+
+/// Output name of a subpath: its path without the leading `./`.
+pub fn name(subpath: &SubpathConfig) -> &str {
+    subpath.path.trim_start_matches("./")
+}
+
+/// Path of a subpath's declarations, relative to the output directory, or
+/// None for a JS source without `types`.
+pub fn types_path(subpath: &SubpathConfig) -> Option<PathBuf> {
+    let file_name = declarations::file_name(&subpath.source, subpath.types.as_deref())?;
+    Some(targets::paths::subpath_types_dir(name(subpath)).join(file_name))
+}
+
+/// Check the configured subpaths before building: well-formed, unique, not
+/// one of the `generated` subpaths, and with existing source files.
+pub fn validate(subpaths: &[SubpathConfig], generated: &[String]) -> Result<()> {
+    for (i, subpath) in subpaths.iter().enumerate() {
+        let path = &subpath.path;
+        let well_formed = path
+            .strip_prefix("./")
+            .is_some_and(|rest| rest.split('/').all(|s| !matches!(s, "" | "." | "..")));
+        if !well_formed || path.contains('*') {
+            anyhow::bail!(
+                "Subpath {path:?} must look like \"./name\" or \"./dir/name\" (no wildcards)"
+            );
+        }
+        if generated.contains(path) {
+            anyhow::bail!("Subpath {path:?} collides with a subpath wasm-bodge generates");
+        }
+        if subpaths[..i].iter().any(|other| &other.path == path) {
+            anyhow::bail!("Subpath {path:?} is configured twice");
+        }
+        if !subpath.source.is_file() {
+            anyhow::bail!(
+                "Source {} of subpath {path:?} not found",
+                subpath.source.display()
+            );
+        }
+        if let Some(types) = subpath.types.as_ref().filter(|t| !t.is_file()) {
+            anyhow::bail!("Types {} of subpath {path:?} not found", types.display());
+        }
+    }
+    Ok(())
+}
+
+/// Bundle each subpath into ESM and CJS, and write its declarations.
+/// Runs after the package's own declarations are in place, which tsc
+/// resolves the package's name to.
+pub fn build(subpaths: &[SubpathConfig], out_dir: &Path, package_json: &Path) -> Result<()> {
+    if subpaths.is_empty() {
+        return Ok(());
+    }
+    let esbuild = find_esbuild()?;
+    // The package's own name resolves to the generated declarations, so a
+    // wrapper importing the bindings from the package type-checks.
+    let package_types = out_dir.canonicalize()?.join(targets::paths::types());
+    let paths = json!({ declarations::npm_name(package_json)?: [package_types] });
+
+    for subpath in subpaths {
+        println!("  Building subpath {}...", subpath.path);
+        let name = name(subpath);
+        bundle(
+            &esbuild,
+            &subpath.source,
+            &out_dir.join(targets::paths::subpath_esm(name)),
+            "esm",
+        )?;
+        bundle(
+            &esbuild,
+            &subpath.source,
+            &out_dir.join(targets::paths::subpath_cjs(name)),
+            "cjs",
+        )?;
+        declarations::write(
+            &subpath.source,
+            subpath.types.as_deref(),
+            &out_dir.join(targets::paths::subpath_types_dir(name)),
+            paths.clone(),
+            &format!("subpath {:?}", subpath.path),
+        )?;
+        declarations::write_formats(&out_dir.join(targets::paths::subpath_types_dir(name)))?;
+    }
+    Ok(())
+}
+
+fn bundle(esbuild: &str, input: &Path, output: &Path, format: &str) -> Result<()> {
+    let platform = if format == "cjs" { "node" } else { "neutral" };
+    let status = Command::new(esbuild)
+        .arg(input)
+        .args([
+            "--bundle",
+            "--packages=external",
+            &format!("--format={format}"),
+            &format!("--platform={platform}"),
+            &format!("--outfile={}", output.display()),
+        ])
+        .status()
+        .with_context(|| format!("Failed to run esbuild for {}", input.display()))?;
+
+    if !status.success() {
+        anyhow::bail!("esbuild {format} build of {} failed", input.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_subpaths() {
+        let source = std::env::temp_dir().join("wasm-bodge-test-subpath.ts");
+        std::fs::write(&source, "export const x = 1;\n").unwrap();
+        let subpath = |path: &str| SubpathConfig {
+            path: path.to_string(),
+            source: source.clone(),
+            types: None,
+        };
+        let generated = vec![".".to_string(), "./slim".to_string()];
+        let error =
+            |subpaths: &[SubpathConfig]| validate(subpaths, &generated).unwrap_err().to_string();
+
+        validate(&[subpath("./helpers"), subpath("./utils/x")], &generated).unwrap();
+        assert!(error(&[subpath("./slim")]).contains("collides"));
+        assert!(error(&[subpath("./a"), subpath("./a")]).contains("configured twice"));
+        for bad in ["helpers", "./", "./a/../b", "./*", "./a/"] {
+            assert!(error(&[subpath(bad)]).contains("must look like"), "{bad}");
+        }
+
+        assert_eq!(
+            types_path(&subpath("./utils/x")),
+            Some(PathBuf::from(
+                "subpath-types/utils/x/wasm-bodge-test-subpath.d.ts"
+            ))
+        );
+        let _ = std::fs::remove_file(&source);
+    }
+}
@@ -0,0 +1,156 @@
+//! Symbol maps for the release variant.
+//!
+//! wasm-opt drops the release wasm's name section and DWARF, so browser
+//! stack traces only show `wasm-function[N]`. When a symbol map is requested
+//! wasm-opt runs with `-g`, which keeps function names through optimization
+//! and wasm-bindgen. The names are read from the wasm-bindgen output into an
+//! off-package JSON file and then stripped again, so the packaged wasm is no
+//! bigger than without a symbol map.
+//!
+//! Source locations come from the pre-opt wasm's DWARF. wasm-opt renumbers
+//! functions, so they're matched up by name: each function gets the
+//! declaration line of the `DW_TAG_subprogram` whose code starts in its body.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use super::dwarf;
+use super::wasm_binary::{self, Section};
+use crate::symbolicate::{SYMBOL_MAP_VERSION, Symbol, SymbolMap};
+
+/// v0 crate disambiguators and legacy hash suffixes in demangled names.
+static HASHES: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[[0-9a-f]{16}\]|::h[0-9a-f]{16}$").unwrap());
+
+/// Source file and line of a function, keyed by mangled name.
+pub type FunctionLocations = HashMap<String, (String, u32)>;
+
+/// Read each named function's source location from the DWARF in `wasm`.
+pub fn function_locations(wasm: &[u8]) -> Result<FunctionLocations> {
+    let sections = wasm_binary::sections(wasm)?;
+    let subprograms = dwarf::subprograms(&sections).context("Failed to read DWARF")?;
+    let bodies = wasm_binary::function_bodies(&sections)?;
+    let imported = wasm_binary::imported_function_count(wasm)?;
+
+    let mut locations = FunctionLocations::new();
+    for (index, name) in wasm_binary::function_names(&sections)? {
+        let Some(body) = index
+            .checked_sub(imported)
+            .and_then(|i| bodies.get(i as usize))
+        else {
+            continue;
+        };
+        let first = subprograms.partition_point(|s| s.low_pc < body.start);
+        if let Some(subprogram) = subprograms.get(first).filter(|s| body.contains(&s.low_pc)) {
+            locations.insert(name, (subprogram.file.clone(), subprogram.line));
+        }
+    }
+    Ok(locations)
+}
+
+/// Build the symbol map for `wasm` from its name section and `locations`.
+pub fn symbol_map(wasm: &[u8], locations: &FunctionLocations) -> Result<SymbolMap> {
+    let sections = wasm_binary::sections(wasm)?;
+    let mut map = SymbolMap {
+        version: SYMBOL_MAP_VERSION,
+        ..Default::default()
+    };
+    for (index, name) in wasm_binary::function_names(&sections)? {
+        let location = locations.get(&name);
+        map.functions.insert(
+            index,
+            Symbol {
+                name: display_name(&name),
+                file: location.map(|(file, _)| file.clone()),
+                line: location.map(|(_, line)| *line),
+            },
+        );
+    }
+    Ok(map)
+}
+
+/// Human-readable form of a name-section entry. wasm-ld usually demangles
+/// names already but keeps v0 crate disambiguators (`core[c5930c85a12de822]`)
+/// and legacy hash suffixes (`::h0123456789abcdef`), which are dropped here.
+fn display_name(name: &str) -> String {
+    let demangled = format!("{:#}", rustc_demangle::demangle(name));
+    HASHES.replace_all(&demangled, "").into_owned()
+}
+
+/// Rewrite the wasm at `path` without the sections matching `strip`.
+pub fn strip_sections(path: &Path, strip: impl Fn(&Section<'_>) -> bool) -> Result<()> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let sections = wasm_binary::sections(&bytes)?;
+    let kept = sections.iter().filter(|s| !strip(s)).copied();
+    std::fs::write(path, wasm_binary::encode(kept))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_map_demangles_by_final_index() {
+        // One imported function, so the defined function is index 1
+        let import = [0x01, 0x03, b'e', b'n', b'v', 0x01, b'f', 0x00, 0x00];
+        let code = [0x01, 0x02, 0x00, 0x0b];
+        let mut names = vec![0x01, 0x00];
+        let mut function_names = vec![0x01, 0x01];
+        wasm_binary::write_name(
+            &mut function_names,
+            "_ZN13test_wasm_lib5greet17h0123456789abcdefE",
+        );
+        names[1] = function_names.len() as u8;
+        names.extend(function_names);
+        let name = wasm_binary::custom_section_payload("name", &names);
+        let wasm = wasm_binary::encode([
+            Section {
+                id: 2,
+                payload: &import,
+            },
+            Section {
+                id: wasm_binary::CODE_SECTION,
+                payload: &code,
+            },
+            Section {
+                id: 0,
+                payload: &name,
+            },
+        ]);
+
+        let mut locations = FunctionLocations::new();
+        locations.insert(
+            "_ZN13test_wasm_lib5greet17h0123456789abcdefE".to_string(),
+            ("/src/lib.rs".to_string(), 11),
+        );
+        let map = symbol_map(&wasm, &locations).unwrap();
+        assert_eq!(
+            map.functions.get(&1),
+            Some(&Symbol {
+                name: "test_wasm_lib::greet".to_string(),
+                file: Some("/src/lib.rs".to_string()),
+                line: Some(11),
+            })
+        );
+
+        assert_eq!(
+            display_name(
+                "<alloc[3ca501edff3f0c7c]::string::String as core[c5930c85a12de822]::fmt::Write>::write_char[1]"
+            ),
+            "<alloc::string::String as core::fmt::Write>::write_char[1]"
+        );
+        assert_eq!(
+            display_name("<&T as core::fmt::Display>::fmt::hdcea445b0cfc83f0"),
+            "<&T as core::fmt::Display>::fmt"
+        );
+
+        let sections = wasm_binary::sections(&wasm).unwrap();
+        assert_eq!(wasm_binary::imported_function_count(&wasm).unwrap(), 1);
+        assert_eq!(wasm_binary::function_bodies(&sections).unwrap(), vec![2..4]);
+    }
+}
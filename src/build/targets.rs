@@ -10,6 +10,9 @@
 //! definition below.
 
 use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{AssetUrlCondition, ConditionConfig, WasmCompression};
 
 /// The wasm-bindgen CLI targets we use.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -106,10 +109,29 @@ pub enum InitStrategy {
     NodeFsSync,
     /// Auto-initializes by embedding wasm as base64
     Base64Embedded,
+    /// Like Base64Embedded, but instantiates asynchronously with top-level
+    /// await and exports a `ready` promise (large wasm in browsers)
+    Base64Async,
+    /// Like Base64Async, but initializes from the wasm a page provides in
+    /// `globalThis.__wasmBodgeWasm` if any, and only imports the base64
+    /// module otherwise
+    Base64Overridable,
     /// Auto-initializes via synchronous wasm import (workerd)
     SyncWasmImport,
+    /// Like SyncWasmImport, with the `?module` suffix Vercel's edge runtime
+    /// needs to import a compiled `WebAssembly.Module` (Wrangler accepts it too)
+    SyncWasmModuleImport,
     /// Imports wasm via bundler target, injects into web target bindings
     BundlerShim,
+    /// Like BundlerShim, but the runtime's own wasm ESM integration loads
+    /// the wasm import instead of a bundler (Deno 2.1+)
+    NativeWasmImport,
+    /// Imports the wasm's path with `with { type: 'file' }`, which Bun embeds
+    /// in `bun build --compile` executables, then reads it and calls initSync
+    BunFileImport,
+    /// Fetches wasm from a `new URL(..., import.meta.url)` asset and
+    /// instantiates it with `instantiateStreaming` (top-level await)
+    AssetUrl,
     /// No initialization - user must call initSync manually
     Manual,
 }
@@ -125,8 +147,17 @@ pub enum Environment {
     Bundler,
     /// Cloudflare Workers (workerd runtime)
     Workerd,
-    /// Script tag usage (IIFE)
-    #[expect(dead_code)]
+    /// Deno (including `npm:` specifiers)
+    Deno,
+    /// Bun (including `bun build --compile` executables)
+    Bun,
+    /// Vercel Edge and other runtimes resolving "edge-light"
+    EdgeLight,
+    /// WinterCG runtimes and web workers resolving "worker"
+    Worker,
+    /// Bundlers without wasm ESM integration and native browser ESM
+    AssetUrl,
+    /// Script tag usage (IIFE or UMD, configured by `[iife]`)
     Iife,
     /// Manual initialization (escape hatch)
     Slim,
@@ -140,8 +171,13 @@ impl Environment {
             Self::Web,
             Self::Bundler,
             Self::Workerd,
+            Self::Deno,
+            Self::Bun,
+            Self::EdgeLight,
+            Self::Worker,
+            Self::AssetUrl,
             Self::Slim,
-            // Note: IIFE is handled specially (bundled from Web)
+            // Note: IIFE has no shipped ESM entrypoint, only a bundle input
         ]
     }
 
@@ -152,7 +188,12 @@ impl Environment {
             Self::Web => "web",
             Self::Bundler => "bundler",
             Self::Workerd => "workerd",
-            Self::Iife => "index", // in iife/ subdir
+            Self::Deno => "deno",
+            Self::Bun => "bun",
+            Self::EdgeLight => "edge-light",
+            Self::Worker => "worker",
+            Self::AssetUrl => "asset-url",
+            Self::Iife => "iife",
             Self::Slim => "slim",
         }
     }
@@ -165,7 +206,12 @@ impl Environment {
             Self::Web => WasmBindgenTarget::Web,
             Self::Bundler => WasmBindgenTarget::Web,
             Self::Workerd => WasmBindgenTarget::Web,
-            Self::Iife => WasmBindgenTarget::Web, // bundled from web.js
+            Self::Deno => WasmBindgenTarget::Web,
+            Self::Bun => WasmBindgenTarget::Web,
+            Self::EdgeLight => WasmBindgenTarget::Web,
+            Self::Worker => WasmBindgenTarget::Web,
+            Self::AssetUrl => WasmBindgenTarget::Web,
+            Self::Iife => WasmBindgenTarget::Web,
             Self::Slim => WasmBindgenTarget::Web,
         }
     }
@@ -177,6 +223,13 @@ impl Environment {
             Self::Web => InitStrategy::Base64Embedded,
             Self::Bundler => InitStrategy::BundlerShim,
             Self::Workerd => InitStrategy::SyncWasmImport,
+            Self::Deno => InitStrategy::NativeWasmImport,
+            Self::Bun => InitStrategy::BunFileImport,
+            Self::EdgeLight => InitStrategy::SyncWasmModuleImport,
+            // Worker runtimes differ in how (and whether) they import wasm,
+            // but all of them can instantiate bytes asynchronously
+            Self::Worker => InitStrategy::Base64Async,
+            Self::AssetUrl => InitStrategy::AssetUrl,
             Self::Iife => InitStrategy::Base64Embedded,
             Self::Slim => InitStrategy::Manual,
         }
@@ -196,14 +249,94 @@ impl Environment {
             Self::Bundler => false,
             // Workerd CJS falls back to web.cjs (specified in ROOT_EXPORT_MAPPING)
             Self::Workerd => false,
+            // Deno CJS falls back to web.cjs (specified in ROOT_EXPORT_MAPPING)
+            Self::Deno => false,
+            // Bun CJS embeds the wasm instead of importing it as a file
+            Self::Bun => true,
+            // Edge and worker CJS fall back to web.cjs (specified in ROOT_EXPORT_MAPPING)
+            Self::EdgeLight | Self::Worker => false,
+            // import.meta.url has no CJS equivalent; CJS keeps the web.cjs fallback
+            Self::AssetUrl => false,
             // IIFE doesn't have a CJS variant
             Self::Iife => false,
         }
     }
+
+    /// Whether a CommonJS entrypoint exists for this environment, generated
+    /// or bundled.
+    pub fn has_cjs_entrypoint(&self) -> bool {
+        self.needs_cjs_bundle() || matches!(self, Self::Node | Self::Slim)
+    }
+}
+
+impl FromStr for Environment {
+    type Err = anyhow::Error;
+
+    /// Parse an environment by its entrypoint file stem (e.g. "web", "edge-light").
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Self::all()
+            .iter()
+            .find(|env| env.file_stem() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::all().iter().map(|env| env.file_stem()).collect();
+                anyhow::anyhow!(
+                    "Unknown environment {s:?}; expected one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Build-wide settings that change the content of generated entrypoints.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EntrypointOptions {
+    /// Compression of the wasm embedded in base64 entrypoints
+    pub compression: Option<WasmCompression>,
+    /// Initialize base64 entrypoints asynchronously instead of with initSync
+    pub async_init: bool,
+    /// Write external source maps for the esbuild bundles (CJS and IIFE)
+    pub source_map: bool,
+    /// Minify the esbuild bundles
+    pub minify: bool,
+    /// Node ESM entrypoints re-export the CJS ones (one wasm instance per
+    /// process)
+    pub shared_node_instance: bool,
+    /// Base64 entrypoints let the page provide the wasm at runtime
+    pub wasm_source_override: bool,
+}
+
+impl EntrypointOptions {
+    /// How `env` initializes the wasm module under these options.
+    pub fn init_strategy(&self, env: Environment) -> InitStrategy {
+        match env.init_strategy() {
+            InitStrategy::Base64Embedded | InitStrategy::Base64Async
+                if self.wasm_source_override =>
+            {
+                InitStrategy::Base64Overridable
+            }
+            InitStrategy::Base64Embedded if self.async_init => InitStrategy::Base64Async,
+            strategy => strategy,
+        }
+    }
+
+    /// Whether `env`'s ESM entrypoint re-exports its CJS entrypoint instead
+    /// of initializing its own instance.
+    pub fn reexports_cjs(&self, env: Environment) -> bool {
+        self.shared_node_instance && env == Environment::Node
+    }
+
+    /// Whether `env`'s ESM entrypoint exports a `ready` promise.
+    pub fn exports_ready(&self, env: Environment) -> bool {
+        matches!(
+            self.init_strategy(env),
+            InitStrategy::Base64Async | InitStrategy::Base64Overridable | InitStrategy::AssetUrl
+        )
+    }
 }
 
 /// An export condition in package.json (e.g., "node", "browser", "import")
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ExportCondition {
     /// "node" - Node.js runtime
     Node,
@@ -211,26 +344,68 @@ pub enum ExportCondition {
     Browser,
     /// "workerd" - Cloudflare Workers runtime
     Workerd,
+    /// "deno" - Deno runtime
+    Deno,
+    /// "bun" - Bun runtime
+    Bun,
+    /// "edge-light" - Vercel Edge runtime
+    EdgeLight,
+    /// "worker" - WinterCG runtimes and web workers
+    Worker,
     /// "import" - ES Module import (fallback)
     Import,
     /// "require" - CommonJS require (fallback)
     Require,
+    /// Any other condition name, from the config file
+    Custom(String),
 }
 
 impl ExportCondition {
-    pub fn as_str(&self) -> &'static str {
+    /// The known condition called `name`, or a custom one.
+    pub fn parse(name: &str) -> Self {
+        [
+            Self::Node,
+            Self::Browser,
+            Self::Workerd,
+            Self::Deno,
+            Self::Bun,
+            Self::EdgeLight,
+            Self::Worker,
+            Self::Import,
+            Self::Require,
+        ]
+        .into_iter()
+        .find(|condition| condition.as_str() == name)
+        .unwrap_or_else(|| Self::Custom(name.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
         match self {
             Self::Node => "node",
             Self::Browser => "browser",
             Self::Workerd => "workerd",
+            Self::Deno => "deno",
+            Self::Bun => "bun",
+            Self::EdgeLight => "edge-light",
+            Self::Worker => "worker",
             Self::Import => "import",
             Self::Require => "require",
+            Self::Custom(name) => name,
+        }
+    }
+}
+
+impl From<AssetUrlCondition> for ExportCondition {
+    fn from(condition: AssetUrlCondition) -> Self {
+        match condition {
+            AssetUrlCondition::Browser => Self::Browser,
+            AssetUrlCondition::Import => Self::Import,
         }
     }
 }
 
 /// Defines how a package.json export condition maps to environments.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct ExportMapping {
     /// The condition name in package.json (e.g., "node", "browser")
     pub condition: ExportCondition,
@@ -240,7 +415,8 @@ pub struct ExportMapping {
     pub cjs: Environment,
 }
 
-/// Mapping from export conditions to environments for the root "." export.
+/// Default mapping from export conditions to environments for the root "."
+/// export, unless the config file's `[[conditions]]` replace it.
 ///
 /// This defines which environment handles each condition. The order matters
 /// for how package.json exports are structured (more specific conditions first).
@@ -251,11 +427,33 @@ pub const ROOT_EXPORT_MAPPING: &[ExportMapping] = &[
         esm: Environment::Workerd,
         cjs: Environment::Web,
     },
+    ExportMapping {
+        condition: ExportCondition::EdgeLight,
+        esm: Environment::EdgeLight,
+        cjs: Environment::Web,
+    },
+    // Deno and Bun also match "node", so they have to come first
+    ExportMapping {
+        condition: ExportCondition::Deno,
+        esm: Environment::Deno,
+        cjs: Environment::Web,
+    },
+    ExportMapping {
+        condition: ExportCondition::Bun,
+        esm: Environment::Bun,
+        cjs: Environment::Bun,
+    },
     ExportMapping {
         condition: ExportCondition::Node,
         esm: Environment::Node,
         cjs: Environment::Node,
     },
+    // Edge runtimes and bundlers targeting web workers also match "browser"
+    ExportMapping {
+        condition: ExportCondition::Worker,
+        esm: Environment::Worker,
+        cjs: Environment::Web,
+    },
     ExportMapping {
         condition: ExportCondition::Browser,
         esm: Environment::Bundler,
@@ -274,6 +472,93 @@ pub const ROOT_EXPORT_MAPPING: &[ExportMapping] = &[
     },
 ];
 
+/// `mapping` with the ESM side of each of `asset_url_conditions` switched to
+/// the asset-URL environment. CJS is unchanged.
+pub fn with_asset_url_conditions(
+    mapping: Vec<ExportMapping>,
+    asset_url_conditions: &[ExportCondition],
+) -> Vec<ExportMapping> {
+    mapping
+        .into_iter()
+        .map(|mapping| ExportMapping {
+            esm: if asset_url_conditions.contains(&mapping.condition) {
+                Environment::AssetUrl
+            } else {
+                mapping.esm
+            },
+            ..mapping
+        })
+        .collect()
+}
+
+/// Build the export mapping from the config file's `[[conditions]]`.
+///
+/// Rejects unknown environments, CJS environments without a CommonJS
+/// entrypoint, duplicate conditions, and conditions listed after both
+/// `import` and `require`, which no resolver would ever reach.
+pub fn configured_export_mapping(
+    entries: &[ConditionConfig],
+) -> anyhow::Result<Vec<ExportMapping>> {
+    let mut mapping: Vec<ExportMapping> = Vec::new();
+    let (mut seen_import, mut seen_require) = (false, false);
+
+    for entry in entries {
+        let condition = ExportCondition::parse(&entry.condition);
+        if condition.as_str() == "types" {
+            anyhow::bail!("The `types` condition is generated and can't be configured");
+        }
+        if mapping.iter().any(|m| m.condition == condition) {
+            anyhow::bail!("Condition {:?} is configured twice", entry.condition);
+        }
+        if seen_import && seen_require {
+            anyhow::bail!(
+                "Condition {:?} comes after both `import` and `require`, so no resolver \
+                 would reach it. Move it earlier.",
+                entry.condition
+            );
+        }
+
+        let env = |field: &Option<String>, side: &str| -> anyhow::Result<Environment> {
+            let name = field.as_deref().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Condition {:?} needs an `{side}` environment",
+                    entry.condition
+                )
+            })?;
+            name.parse()
+                .map_err(|e| anyhow::anyhow!("Condition {:?}: {e}", entry.condition))
+        };
+        // `import` only resolves ESM and `require` only CJS; the other side
+        // is never written to package.json.
+        let esm = match condition {
+            ExportCondition::Require => Environment::Web,
+            _ => env(&entry.esm, "esm")?,
+        };
+        let cjs = match condition {
+            ExportCondition::Import => Environment::Web,
+            _ => env(&entry.cjs, "cjs")?,
+        };
+        if !cjs.has_cjs_entrypoint() {
+            anyhow::bail!(
+                "Condition {:?}: the {:?} environment has no CommonJS entrypoint; use \
+                 `cjs = \"web\"`, `\"node\"` or `\"slim\"`",
+                entry.condition,
+                cjs.file_stem()
+            );
+        }
+
+        seen_import |= condition == ExportCondition::Import;
+        seen_require |= condition == ExportCondition::Require;
+        mapping.push(ExportMapping {
+            condition,
+            esm,
+            cjs,
+        });
+    }
+
+    Ok(mapping)
+}
+
 // ============================================================================
 // Path helpers - centralized path construction using PathBuf
 // ============================================================================
@@ -293,12 +578,59 @@ pub mod paths {
         PathBuf::from("esm").join(format!("{}{}.js", variant.file_prefix(), env.file_stem()))
     }
 
+    /// Path to the temporary esbuild input for async entrypoints:
+    /// esm/{prefix}{env}.bundle-input.js (removed after bundling)
+    pub fn esm_bundle_input(env: Environment, variant: WasmVariant) -> PathBuf {
+        PathBuf::from("esm").join(format!(
+            "{}{}.bundle-input.js",
+            variant.file_prefix(),
+            env.file_stem()
+        ))
+    }
+
+    /// Path to the bindings module a facade entrypoint wraps:
+    /// esm/{prefix}{env}.bindings.js
+    pub fn esm_bindings(env: Environment, variant: WasmVariant) -> PathBuf {
+        PathBuf::from("esm").join(format!(
+            "{}{}.bindings.js",
+            variant.file_prefix(),
+            env.file_stem()
+        ))
+    }
+
+    /// Path to the temporary esbuild input for facade entrypoints:
+    /// esm/{prefix}{env}.facade-input.js (removed after bundling)
+    pub fn esm_facade_input(env: Environment, variant: WasmVariant) -> PathBuf {
+        PathBuf::from("esm").join(format!(
+            "{}{}.facade-input.js",
+            variant.file_prefix(),
+            env.file_stem()
+        ))
+    }
+
+    /// Path to `./slim`'s ESM entrypoint for Node with a shared instance:
+    /// esm/{prefix}slim-node.js
+    pub fn esm_node_slim(variant: WasmVariant) -> PathBuf {
+        PathBuf::from("esm").join(format!("{}slim-node.js", variant.file_prefix()))
+    }
+
     /// Path to CJS entrypoint: cjs/{prefix}{env}.cjs
     pub fn cjs_entrypoint(env: Environment, variant: WasmVariant) -> PathBuf {
         PathBuf::from("cjs").join(format!("{}{}.cjs", variant.file_prefix(), env.file_stem()))
     }
 
-    /// Path to IIFE bundle: iife/index.js or iife/debug.js
+    /// Path to the bindings module a generated CJS facade entrypoint wraps:
+    /// cjs/{prefix}{env}.bindings.cjs
+    pub fn cjs_bindings(env: Environment, variant: WasmVariant) -> PathBuf {
+        PathBuf::from("cjs").join(format!(
+            "{}{}.bindings.cjs",
+            variant.file_prefix(),
+            env.file_stem()
+        ))
+    }
+
+    /// Path to IIFE bundle: iife/index.js or iife/debug.js (UMD with
+    /// `format = "umd"`)
     pub fn iife_bundle(variant: WasmVariant) -> PathBuf {
         match variant {
             WasmVariant::Optimized => PathBuf::from("iife/index.js"),
@@ -311,11 +643,42 @@ pub mod paths {
         PathBuf::from(format!("esm/{}wasm-base64.js", variant.file_prefix()))
     }
 
+    /// Path to the variant's build info module (ESM): esm/{prefix}build-info.js
+    pub fn build_info_esm(variant: WasmVariant) -> PathBuf {
+        PathBuf::from(format!("esm/{}build-info.js", variant.file_prefix()))
+    }
+
+    /// Path to the variant's build info module (CJS): cjs/{prefix}build-info.cjs
+    pub fn build_info_cjs(variant: WasmVariant) -> PathBuf {
+        PathBuf::from(format!("cjs/{}build-info.cjs", variant.file_prefix()))
+    }
+
     /// Path to base64 wasm module (CJS): cjs/{prefix}wasm-base64.cjs
     pub fn wasm_base64_cjs(variant: WasmVariant) -> PathBuf {
         PathBuf::from(format!("cjs/{}wasm-base64.cjs", variant.file_prefix()))
     }
 
+    /// Path to gzip-compressed base64 wasm module (ESM): esm/{prefix}wasm-base64-gzip.js
+    pub fn wasm_base64_gzip_esm(variant: WasmVariant) -> PathBuf {
+        PathBuf::from(format!("esm/{}wasm-base64-gzip.js", variant.file_prefix()))
+    }
+
+    /// Path to the base64 decoder (ESM, shared across variants): esm/decode-base64.js
+    pub fn decode_base64_esm() -> PathBuf {
+        PathBuf::from("esm/decode-base64.js")
+    }
+
+    /// Path to the runtime wasm source lookup (ESM, shared across variants):
+    /// esm/wasm-source.js
+    pub fn wasm_source_esm() -> PathBuf {
+        PathBuf::from("esm/wasm-source.js")
+    }
+
+    /// Path to the bundled gzip decoder (ESM, shared across variants): esm/gunzip.js
+    pub fn gunzip_esm() -> PathBuf {
+        PathBuf::from("esm/gunzip.js")
+    }
+
     /// Path to CJS web bindings bundle: cjs/{prefix}web-bindings.cjs
     ///
     /// Bundled per variant -- wasm-opt renames wasm exports in the optimized
@@ -326,11 +689,62 @@ pub mod paths {
         PathBuf::from(format!("cjs/{}web-bindings.cjs", variant.file_prefix()))
     }
 
-    /// Path to TypeScript declarations: index.d.ts
+    /// Path to TypeScript declarations of the auto-initializing
+    /// entrypoints: index.d.ts
     pub fn types() -> PathBuf {
         PathBuf::from("index.d.ts")
     }
 
+    /// Path to TypeScript declarations of the manually initialized slim
+    /// entrypoints: {prefix}slim.d.ts
+    ///
+    /// Per variant, since they describe the raw wasm exports (`InitOutput`),
+    /// which wasm-opt renames in the optimized variant.
+    pub fn slim_types(variant: WasmVariant) -> PathBuf {
+        PathBuf::from(format!("{}slim.d.ts", variant.file_prefix()))
+    }
+
+    /// Path to the declaration of the IIFE bundle's global: iife/index.d.ts
+    /// or iife/debug.d.ts, next to the bundle
+    pub fn iife_types(variant: WasmVariant) -> PathBuf {
+        iife_bundle(variant).with_extension("d.ts")
+    }
+
+    /// Path to TypeScript declarations of the asset-URL entrypoints:
+    /// asset-url.d.ts
+    pub fn asset_url_types() -> PathBuf {
+        PathBuf::from("asset-url.d.ts")
+    }
+
+    /// Path to TypeScript declarations of the base64 wasm modules:
+    /// wasm-base64.d.ts
+    pub fn wasm_base64_types() -> PathBuf {
+        PathBuf::from("wasm-base64.d.ts")
+    }
+
+    /// Directory for the facade's declarations: facade-types/
+    pub fn facade_types_dir() -> PathBuf {
+        PathBuf::from("facade-types")
+    }
+
+    /// Path to a custom subpath's ESM module: subpaths/{name}.js
+    pub fn subpath_esm(name: &str) -> PathBuf {
+        PathBuf::from(format!("subpaths/{name}.js"))
+    }
+
+    /// Path to a custom subpath's CJS module: subpaths/{name}.cjs
+    pub fn subpath_cjs(name: &str) -> PathBuf {
+        PathBuf::from(format!("subpaths/{name}.cjs"))
+    }
+
+    /// Directory for a custom subpath's declarations: subpath-types/{name}/
+    ///
+    /// A directory rather than a single file, because tsc emits one
+    /// declaration file per local module the source imports.
+    pub fn subpath_types_dir(name: &str) -> PathBuf {
+        PathBuf::from(format!("subpath-types/{name}"))
+    }
+
     /// Path to standalone wasm file: {package_name}.wasm or {package_name}-debug.wasm
     pub fn standalone_wasm(package_name: &str, variant: WasmVariant) -> PathBuf {
         match variant {
@@ -349,12 +763,78 @@ pub mod paths {
 /// Each variant references its own wasm-bindgen JS output (wasm_bindgen/web[-debug]/)
 /// because wasm-opt renames wasm exports in the optimized variant so the JS
 /// bindings diverge between variants.
-pub fn generate_esm_entrypoint(env: Environment, wasm_name: &str, variant: WasmVariant) -> String {
+///
+/// `options` only affects the base64-embedded entrypoints: compression makes
+/// them import the compressed payload and decompress it before init, and
+/// async init swaps `initSync` for an awaited `ready` promise.
+pub fn generate_esm_entrypoint(
+    env: Environment,
+    wasm_name: &str,
+    variant: WasmVariant,
+    options: &EntrypointOptions,
+) -> String {
+    let strategy = options.init_strategy(env);
+    esm_entrypoint(env, wasm_name, variant, options, strategy, true)
+}
+
+/// Generates the ESM input that esbuild bundles into CJS or IIFE, if it
+/// differs from the shipped ESM entrypoint (or, for the IIFE, there is none).
+///
+/// Neither format supports top-level await, so async entrypoints are bundled
+/// from a copy that only exports the `ready` promise without awaiting it.
+/// esbuild can't bundle Bun's `type: 'file'` wasm import, so Bun's CJS
+/// bundle embeds the wasm in base64 instead, which also keeps it inside
+/// `bun build --compile` executables.
+pub fn generate_esm_bundle_input(
+    env: Environment,
+    wasm_name: &str,
+    variant: WasmVariant,
+    options: &EntrypointOptions,
+) -> Option<String> {
+    match options.init_strategy(env) {
+        strategy @ (InitStrategy::Base64Async | InitStrategy::Base64Overridable) => Some(
+            esm_entrypoint(env, wasm_name, variant, options, strategy, false),
+        ),
+        InitStrategy::BunFileImport => Some(esm_entrypoint(
+            env,
+            wasm_name,
+            variant,
+            options,
+            InitStrategy::Base64Embedded,
+            false,
+        )),
+        strategy if env == Environment::Iife => Some(esm_entrypoint(
+            env, wasm_name, variant, options, strategy, false,
+        )),
+        _ => None,
+    }
+}
+
+fn esm_entrypoint(
+    env: Environment,
+    wasm_name: &str,
+    variant: WasmVariant,
+    options: &EntrypointOptions,
+    strategy: InitStrategy,
+    top_level_await: bool,
+) -> String {
     let web_dir = format!("wasm_bindgen/web{}", variant.dir_suffix());
     let bundler_wasm_dir = format!("wasm_bindgen/bundler{}", variant.dir_suffix());
     let base64_import = format!("./{}wasm-base64.js", variant.file_prefix());
+    let base64_gzip_import = format!("./{}wasm-base64-gzip.js", variant.file_prefix());
+    let gzip = options.compression == Some(WasmCompression::Gzip);
 
-    match env.init_strategy() {
+    if options.reexports_cjs(env) {
+        // Node loads the CJS entrypoint once for `import` and `require`
+        // alike, so both get the same glue and wasm instance. Node finds the
+        // names to re-export with cjs-module-lexer.
+        return format!(
+            "export * from '../{}';\n",
+            paths::cjs_entrypoint(env, variant).display()
+        );
+    }
+
+    let mut content = match strategy {
         InitStrategy::NodeFsSync => {
             // Read wasm from disk and initialize synchronously
             format!(
@@ -370,12 +850,45 @@ export * from '../{web_dir}/{name}.js';
                 web_dir = web_dir,
             )
         }
+        InitStrategy::BunFileImport => {
+            // Bun resolves a `type: 'file'` import to the file's path: on disk
+            // when run normally, inside the executable after
+            // `bun build --compile`. A __dirname-relative path breaks there.
+            // Bun loads any `.wasm` import this way; the attribute only pins it.
+            format!(
+                r#"import {{ initSync }} from '../{web_dir}/{name}.js';
+import {{ readFileSync }} from 'node:fs';
+import wasmPath from '../{web_dir}/{name}_bg.wasm' with {{ type: 'file' }};
+initSync({{ module: readFileSync(wasmPath) }});
+export * from '../{web_dir}/{name}.js';
+"#,
+                name = wasm_name,
+                web_dir = web_dir,
+            )
+        }
+        InitStrategy::Base64Embedded if gzip => {
+            // Import gzipped base64, decode, decompress, init, then re-export
+            format!(
+                r#"import {{ initSync }} from '../{web_dir}/{name}.js';
+import {{ wasmBase64Gzip }} from '{base64_gzip_import}';
+import {{ decodeBase64 }} from './decode-base64.js';
+import {{ gunzipSync }} from './gunzip.js';
+const bytes = gunzipSync(decodeBase64(wasmBase64Gzip));
+initSync({{ module: bytes }});
+export * from '../{web_dir}/{name}.js';
+"#,
+                name = wasm_name,
+                web_dir = web_dir,
+                base64_gzip_import = base64_gzip_import,
+            )
+        }
         InitStrategy::Base64Embedded => {
             // Import base64, decode, init, then re-export
             format!(
                 r#"import {{ initSync }} from '../{web_dir}/{name}.js';
 import {{ wasmBase64 }} from '{base64_import}';
-const bytes = Uint8Array.from(atob(wasmBase64), c => c.charCodeAt(0));
+import {{ decodeBase64 }} from './decode-base64.js';
+const bytes = decodeBase64(wasmBase64);
 initSync({{ module: bytes }});
 export * from '../{web_dir}/{name}.js';
 "#,
@@ -384,6 +897,87 @@ export * from '../{web_dir}/{name}.js';
                 base64_import = base64_import,
             )
         }
+        InitStrategy::Base64Async => {
+            // Decode (and decompress) the embedded wasm, then let the web
+            // target's default init compile it with WebAssembly.instantiate.
+            let (imports, bytes) = if gzip {
+                (
+                    format!(
+                        "import {{ wasmBase64Gzip }} from '{base64_gzip_import}';\n\
+                         import {{ decodeBase64 }} from './decode-base64.js';\n\
+                         import {{ gunzip }} from './gunzip.js';\n"
+                    ),
+                    "gunzip(decodeBase64(wasmBase64Gzip))",
+                )
+            } else {
+                (
+                    format!(
+                        "import {{ wasmBase64 }} from '{base64_import}';\n\
+                         import {{ decodeBase64 }} from './decode-base64.js';\n"
+                    ),
+                    "Promise.resolve(decodeBase64(wasmBase64))",
+                )
+            };
+            format!(
+                r#"import __wbg_init from '../{web_dir}/{name}.js';
+{imports}export const ready = {bytes}
+    .then((bytes) => __wbg_init({{ module_or_path: bytes }}))
+    .then(() => {{}});
+{await_ready}export * from '../{web_dir}/{name}.js';
+"#,
+                name = wasm_name,
+                web_dir = web_dir,
+                imports = imports,
+                bytes = bytes,
+                await_ready = if top_level_await {
+                    "await ready;\n"
+                } else {
+                    ""
+                },
+            )
+        }
+        InitStrategy::Base64Overridable => {
+            // Prefer the page's wasm (URL, Response, Module or bytes, which
+            // the web target's default init all accept). The base64 module
+            // is a dynamic import so that, unbundled or code-split, it is
+            // never downloaded when the page provides the wasm. `__buildInfo`
+            // is imported below with the metadata.
+            let (base64, bytes) = if gzip {
+                (
+                    base64_gzip_import.as_str(),
+                    "gunzip(decodeBase64(m.wasmBase64Gzip))",
+                )
+            } else {
+                (base64_import.as_str(), "decodeBase64(m.wasmBase64)")
+            };
+            format!(
+                r#"import __wbg_init from '../{web_dir}/{name}.js';
+import {{ decodeBase64 }} from './decode-base64.js';
+{gunzip}import {{ wasmSource }} from './wasm-source.js';
+const source = wasmSource(__buildInfo);
+export const ready = (source !== undefined
+    ? Promise.resolve(source)
+    : import('{base64}').then((m) => {bytes}))
+    .then((module_or_path) => __wbg_init({{ module_or_path }}))
+    .then(() => {{}});
+{await_ready}export * from '../{web_dir}/{name}.js';
+"#,
+                name = wasm_name,
+                web_dir = web_dir,
+                gunzip = if gzip {
+                    "import { gunzip } from './gunzip.js';\n"
+                } else {
+                    ""
+                },
+                base64 = base64,
+                bytes = bytes,
+                await_ready = if top_level_await {
+                    "await ready;\n"
+                } else {
+                    ""
+                },
+            )
+        }
         InitStrategy::SyncWasmImport => {
             // Synchronously import wasm module (workerd)
             format!(
@@ -397,12 +991,25 @@ export * from '../{web_dir}/{name}.js';
                 web_dir = web_dir,
             )
         }
-        InitStrategy::BundlerShim => {
-            // Import wasm via bundler target (bundler handles loading), inject
-            // into web target bindings so bundler and slim share wasm state
-            // within this variant. The bundler resolves wasm imports relative
-            // to the _bg.js, so both _bg.js and _bg.wasm come from the same
-            // directory (bundler or bundler-debug).
+        InitStrategy::SyncWasmModuleImport => {
+            // Edge runtimes forbid compiling wasm from bytes at runtime, so
+            // the wasm has to arrive precompiled through the import
+            format!(
+                r#"import {{ initSync }} from '../{web_dir}/{name}.js';
+import wasmModule from '../{web_dir}/{name}_bg.wasm?module';
+initSync({{ module: wasmModule }});
+export * from '../{web_dir}/{name}.js';
+"#,
+                name = wasm_name,
+                web_dir = web_dir,
+            )
+        }
+        InitStrategy::BundlerShim | InitStrategy::NativeWasmImport => {
+            // Import wasm via bundler target (bundler or runtime handles
+            // loading), inject into web target bindings so bundler and slim
+            // share wasm state within this variant. Wasm imports resolve
+            // relative to the _bg.js, so both _bg.js and _bg.wasm come from the
+            // same directory (bundler or bundler-debug).
             format!(
                 r#"import {{ __wbg_set_wasm as __bundler_set_wasm }} from '../{bundler_dir}/{name}_bg.js';
 import * as wasmExports from '../{bundler_dir}/{name}_bg.wasm';
@@ -417,6 +1024,22 @@ export * from '../{web_dir}/{name}.js';
                 bundler_dir = bundler_wasm_dir,
             )
         }
+        InitStrategy::AssetUrl => {
+            // Reference the wasm as an asset so bundlers copy it and rewrite
+            // the URL, and native ESM resolves it next to this file. The web
+            // target's default init fetches it with instantiateStreaming.
+            format!(
+                r#"import __wbg_init from '../{web_dir}/{name}.js';
+export const ready = __wbg_init({{
+    module_or_path: new URL('../{web_dir}/{name}_bg.wasm', import.meta.url),
+}}).then(() => {{}});
+await ready;
+export * from '../{web_dir}/{name}.js';
+"#,
+                name = wasm_name,
+                web_dir = web_dir,
+            )
+        }
         InitStrategy::Manual => {
             // Re-export without initialization (user calls initSync).
             format!(
@@ -425,7 +1048,92 @@ export * from '../{web_dir}/{name}.js';
                 web_dir = web_dir,
             )
         }
+    };
+
+    if !matches!(strategy, InitStrategy::Manual) {
+        let ready = if options.exports_ready(env) {
+            "ready"
+        } else {
+            "Promise.resolve()"
+        };
+        content.push_str(&format!(
+            "import {{ buildInfo as __buildInfo }} from './{build_info}';\n\
+             export const __wasmBodge = {metadata};\n",
+            build_info = paths::build_info_esm(variant)
+                .file_name()
+                .unwrap()
+                .to_string_lossy(),
+            metadata = metadata(env, strategy, ready),
+        ));
     }
+    content
+}
+
+/// The `__wasmBodge` object of an auto-initializing entrypoint: the
+/// variant's build info (variant, package version, wasm hash) plus how this
+/// entrypoint initializes, and `ready`, the expression of a promise that
+/// resolves once the wasm is usable.
+fn metadata(env: Environment, strategy: InitStrategy, ready: &str) -> String {
+    format!(
+        "Object.freeze({{ ...__buildInfo, environment: '{env}', strategy: '{strategy:?}', ready: {ready} }})",
+        env = env.file_stem(),
+    )
+}
+
+/// Generates the build info every `__wasmBodge` of `variant` spreads, as a
+/// JSON object: the variant, the package's name and version and the SHA-256
+/// of the wasm.
+pub fn generate_build_info(
+    variant: WasmVariant,
+    name: Option<&str>,
+    version: Option<&str>,
+    wasm_hash: &str,
+) -> String {
+    serde_json::json!({
+        "variant": if variant.is_debug() { "debug" } else { "optimized" },
+        "name": name,
+        "version": version,
+        "wasmHash": wasm_hash,
+    })
+    .to_string()
+}
+
+/// Name of the variable esbuild assigns the exports to inside the UMD wrapper.
+pub const UMD_EXPORTS: &str = "__wasmBodgeExports";
+
+/// Generates the banner and footer that wrap the IIFE bundle into UMD:
+/// an AMD module if there's a loader with `define.amd`, `module.exports`
+/// under CommonJS, and the `global` global otherwise.
+pub fn generate_umd_wrapper(global: &str) -> (String, String) {
+    let banner = format!(
+        r#"(function (root, factory) {{
+    if (typeof define === 'function' && define.amd) define([], factory);
+    else if (typeof module === 'object' && module.exports) module.exports = factory();
+    else root.{global} = factory();
+}})(typeof self !== 'undefined' ? self : this, function () {{"#
+    );
+    let footer = format!("return {UMD_EXPORTS};\n}});");
+    (banner, footer)
+}
+
+/// Generates the declaration of `global`, the IIFE bundle's global, as a
+/// script (not a module) so that referencing it declares the global.
+/// `module` is the declared module the bundle exports, relative to iife/.
+pub fn generate_iife_types(global: &str, module: &str) -> String {
+    format!(
+        "/** The exports of the script-tag bundle. */\n\
+         declare var {global}: typeof import(\"{module}\");\n"
+    )
+}
+
+/// Generates `./slim`'s ESM entrypoint for Node with a shared instance: the
+/// CJS slim entrypoint, so it shares the glue with the Node entrypoints.
+pub fn generate_esm_node_slim(variant: WasmVariant) -> String {
+    let cjs = paths::cjs_entrypoint(Environment::Slim, variant);
+    format!(
+        "import bindings from '../{cjs}';\nexport * from '../{cjs}';\nexport default bindings.default;\n",
+        cjs = cjs.display(),
+    )
 }
 
 /// Generates the JavaScript content for a CJS entrypoint (if not bundled).
@@ -442,17 +1150,26 @@ pub fn generate_cjs_entrypoint(
 
     match env {
         Environment::Node => {
-            // Load variant's web bindings, read wasm from disk, initialize
+            // Load variant's web bindings, read wasm from disk, initialize.
+            // Exported as `{ ...require(...) }` so Node's cjs-module-lexer
+            // sees the bindings' names when ESM imports this module.
             Some(format!(
                 r#"const bindings = require('{bindings_require}');
 const fs = require('fs');
 const path = require('path');
 bindings.initSync({{ module: fs.readFileSync(path.join(__dirname, '../{wasm_dir}/{name}_bg.wasm')) }});
-module.exports = bindings;
+const {{ buildInfo: __buildInfo }} = require('./{build_info}');
+const __wasmBodge = {metadata};
+module.exports = {{ ...require('{bindings_require}'), __wasmBodge }};
 "#,
                 name = wasm_name,
                 wasm_dir = wasm_dir,
                 bindings_require = bindings_require,
+                build_info = paths::build_info_cjs(variant)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy(),
+                metadata = metadata(env, env.init_strategy(), "Promise.resolve()"),
             ))
         }
         Environment::Slim => {
@@ -516,6 +1233,197 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_deno_export_mapping() {
+        let deno = ROOT_EXPORT_MAPPING
+            .iter()
+            .position(|m| m.condition == ExportCondition::Deno)
+            .unwrap();
+        let node = ROOT_EXPORT_MAPPING
+            .iter()
+            .position(|m| m.condition == ExportCondition::Node)
+            .unwrap();
+        // Deno matches the "node" condition too
+        assert!(deno < node);
+
+        let mapping = &ROOT_EXPORT_MAPPING[deno];
+        assert_eq!(mapping.esm, Environment::Deno);
+        assert_eq!(mapping.cjs, Environment::Web);
+
+        let esm = generate_esm_entrypoint(
+            Environment::Deno,
+            "my_crate",
+            WasmVariant::Debug,
+            &EntrypointOptions::default(),
+        );
+        assert!(esm.contains(
+            "import * as wasmExports from '../wasm_bindgen/bundler-debug/my_crate_bg.wasm'"
+        ));
+        assert!(!esm.contains("Deno.readFile"), "must not need --allow-read");
+    }
+
+    #[test]
+    fn test_bun_export_mapping() {
+        let position = |condition| {
+            ROOT_EXPORT_MAPPING
+                .iter()
+                .position(|m| m.condition == condition)
+                .unwrap()
+        };
+        // Bun matches the "node" condition too
+        assert!(position(ExportCondition::Bun) < position(ExportCondition::Node));
+
+        let mapping = &ROOT_EXPORT_MAPPING[position(ExportCondition::Bun)];
+        assert_eq!(mapping.esm, Environment::Bun);
+        assert_eq!(mapping.cjs, Environment::Bun);
+
+        let esm = generate_esm_entrypoint(
+            Environment::Bun,
+            "my_crate",
+            WasmVariant::Optimized,
+            &EntrypointOptions::default(),
+        );
+        assert!(esm.contains(
+            "import wasmPath from '../wasm_bindgen/web/my_crate_bg.wasm' with { type: 'file' };"
+        ));
+        assert!(!esm.contains("__dirname"));
+
+        // The CJS bundle embeds the wasm, as esbuild can't bundle file imports
+        let cjs = generate_esm_bundle_input(
+            Environment::Bun,
+            "my_crate",
+            WasmVariant::Optimized,
+            &EntrypointOptions::default(),
+        )
+        .unwrap();
+        assert!(cjs.contains("import { wasmBase64 } from './wasm-base64.js';"));
+        assert!(!cjs.contains("type: 'file'"));
+    }
+
+    #[test]
+    fn test_edge_export_mappings() {
+        let position = |condition| {
+            ROOT_EXPORT_MAPPING
+                .iter()
+                .position(|m| m.condition == condition)
+                .unwrap()
+        };
+        // Both are more specific than "browser", which edge runtimes also match
+        assert!(position(ExportCondition::EdgeLight) < position(ExportCondition::Worker));
+        assert!(position(ExportCondition::Worker) < position(ExportCondition::Browser));
+
+        let edge_light = generate_esm_entrypoint(
+            Environment::EdgeLight,
+            "my_crate",
+            WasmVariant::Optimized,
+            &EntrypointOptions::default(),
+        );
+        assert!(
+            edge_light
+                .contains("import wasmModule from '../wasm_bindgen/web/my_crate_bg.wasm?module';")
+        );
+
+        // Worker always instantiates asynchronously, regardless of size
+        assert!(matches!(
+            EntrypointOptions::default().init_strategy(Environment::Worker),
+            InitStrategy::Base64Async
+        ));
+        let worker = generate_esm_entrypoint(
+            Environment::Worker,
+            "my_crate",
+            WasmVariant::Optimized,
+            &EntrypointOptions::default(),
+        );
+        assert!(worker.contains("await ready;"));
+        assert!(!worker.contains("initSync"));
+    }
+
+    #[test]
+    fn test_configured_export_mapping() {
+        let entry = |condition: &str, esm: Option<&str>, cjs: Option<&str>| ConditionConfig {
+            condition: condition.to_string(),
+            esm: esm.map(String::from),
+            cjs: cjs.map(String::from),
+        };
+
+        let mapping = configured_export_mapping(&[
+            entry("react-native", Some("web"), Some("web")),
+            entry("browser", Some("web"), Some("web")),
+            entry("import", Some("bundler"), None),
+            entry("require", None, Some("node")),
+        ])
+        .unwrap();
+        assert_eq!(
+            mapping[0].condition,
+            ExportCondition::Custom("react-native".to_string())
+        );
+        assert_eq!(mapping[1].condition, ExportCondition::Browser);
+        assert_eq!(mapping[1].esm, Environment::Web);
+        assert_eq!(mapping[2].esm, Environment::Bundler);
+        assert_eq!(mapping[3].cjs, Environment::Node);
+
+        let error = |entries: &[ConditionConfig]| {
+            configured_export_mapping(entries).unwrap_err().to_string()
+        };
+        assert!(
+            error(&[entry("browser", Some("nope"), Some("web"))])
+                .contains("Unknown environment \"nope\"")
+        );
+        assert!(
+            error(&[entry("browser", Some("web"), Some("bundler"))])
+                .contains("no CommonJS entrypoint")
+        );
+        assert!(error(&[entry("browser", Some("web"), None)]).contains("needs an `cjs`"));
+        assert!(
+            error(&[
+                entry("import", Some("web"), None),
+                entry("require", None, Some("web")),
+                entry("browser", Some("bundler"), Some("web")),
+            ])
+            .contains("comes after both `import` and `require`")
+        );
+        assert!(
+            error(&[
+                entry("node", Some("node"), Some("node")),
+                entry("node", Some("web"), Some("web")),
+            ])
+            .contains("configured twice")
+        );
+    }
+
+    #[test]
+    fn test_asset_url_conditions() {
+        let mapping =
+            with_asset_url_conditions(ROOT_EXPORT_MAPPING.to_vec(), &[ExportCondition::Browser]);
+        let browser = mapping
+            .iter()
+            .find(|m| m.condition == ExportCondition::Browser)
+            .unwrap();
+        assert_eq!(browser.esm, Environment::AssetUrl);
+        // CJS can't use import.meta.url, so it keeps the base64 fallback
+        assert_eq!(browser.cjs, Environment::Web);
+
+        let import = mapping
+            .iter()
+            .find(|m| m.condition == ExportCondition::Import)
+            .unwrap();
+        assert_eq!(import.esm, Environment::Web);
+
+        let esm = generate_esm_entrypoint(
+            Environment::AssetUrl,
+            "my_crate",
+            WasmVariant::Debug,
+            &EntrypointOptions::default(),
+        );
+        assert!(
+            esm.contains("new URL('../wasm_bindgen/web-debug/my_crate_bg.wasm', import.meta.url)")
+        );
+        assert!(
+            !esm.contains("_bg.wasm';"),
+            "must not import the wasm as a module"
+        );
+    }
+
     #[test]
     fn test_variant_paths() {
         assert_eq!(WasmVariant::Optimized.file_prefix(), "");
@@ -547,23 +1455,264 @@ mod tests {
         // Each variant references its own wasm-bindgen JS output because
         // wasm-opt rewrites wasm export names in the optimized variant and the
         // JS bindings diverge as a result.
-        let node_debug = generate_esm_entrypoint(Environment::Node, "my_crate", WasmVariant::Debug);
+        let node_debug = generate_esm_entrypoint(
+            Environment::Node,
+            "my_crate",
+            WasmVariant::Debug,
+            &EntrypointOptions::default(),
+        );
         assert!(node_debug.contains("from '../wasm_bindgen/web-debug/my_crate.js'"));
         assert!(node_debug.contains("../wasm_bindgen/web-debug/my_crate_bg.wasm"));
         assert!(!node_debug.contains("wasm_bindgen/web/my_crate.js"));
 
-        let bundler_debug =
-            generate_esm_entrypoint(Environment::Bundler, "my_crate", WasmVariant::Debug);
+        let bundler_debug = generate_esm_entrypoint(
+            Environment::Bundler,
+            "my_crate",
+            WasmVariant::Debug,
+            &EntrypointOptions::default(),
+        );
         assert!(bundler_debug.contains("'../wasm_bindgen/bundler-debug/my_crate_bg.js'"));
         assert!(bundler_debug.contains("'../wasm_bindgen/bundler-debug/my_crate_bg.wasm'"));
         assert!(bundler_debug.contains("'../wasm_bindgen/web-debug/my_crate.js'"));
 
         // Optimized keeps its old references
-        let node_opt =
-            generate_esm_entrypoint(Environment::Node, "my_crate", WasmVariant::Optimized);
+        let node_opt = generate_esm_entrypoint(
+            Environment::Node,
+            "my_crate",
+            WasmVariant::Optimized,
+            &EntrypointOptions::default(),
+        );
         assert!(node_opt.contains("from '../wasm_bindgen/web/my_crate.js'"));
     }
 
+    #[test]
+    fn test_gzip_only_changes_base64_entrypoints() {
+        let gzip = EntrypointOptions {
+            compression: Some(WasmCompression::Gzip),
+            ..Default::default()
+        };
+        let web = generate_esm_entrypoint(Environment::Web, "my_crate", WasmVariant::Debug, &gzip);
+        assert!(web.contains("from './debug-wasm-base64-gzip.js'"));
+        assert!(web.contains("gunzipSync("));
+        assert!(!web.contains("'./debug-wasm-base64.js'"));
+
+        for env in [
+            Environment::Node,
+            Environment::Bundler,
+            Environment::Workerd,
+        ] {
+            assert_eq!(
+                generate_esm_entrypoint(env, "my_crate", WasmVariant::Optimized, &gzip),
+                generate_esm_entrypoint(
+                    env,
+                    "my_crate",
+                    WasmVariant::Optimized,
+                    &EntrypointOptions::default()
+                ),
+            );
+        }
+    }
+
+    #[test]
+    fn test_async_init_awaits_only_in_esm() {
+        let options = EntrypointOptions {
+            async_init: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            options.init_strategy(Environment::Web),
+            InitStrategy::Base64Async
+        ));
+        assert!(matches!(
+            options.init_strategy(Environment::Node),
+            InitStrategy::NodeFsSync
+        ));
+
+        let esm = generate_esm_entrypoint(
+            Environment::Web,
+            "my_crate",
+            WasmVariant::Optimized,
+            &options,
+        );
+        assert!(esm.contains("export const ready = "));
+        assert!(esm.contains("\nawait ready;\n"));
+        assert!(!esm.contains("initSync"));
+
+        // CJS and IIFE can't use top-level await
+        let bundle_input = generate_esm_bundle_input(
+            Environment::Web,
+            "my_crate",
+            WasmVariant::Optimized,
+            &options,
+        )
+        .unwrap();
+        assert!(bundle_input.contains("export const ready = "));
+        assert!(!bundle_input.contains("await ready"));
+
+        // Synchronous entrypoints are bundled as-is
+        assert!(
+            generate_esm_bundle_input(
+                Environment::Web,
+                "my_crate",
+                WasmVariant::Optimized,
+                &EntrypointOptions::default()
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_entrypoints_export_metadata() {
+        let node = generate_esm_entrypoint(
+            Environment::Node,
+            "my_crate",
+            WasmVariant::Debug,
+            &EntrypointOptions::default(),
+        );
+        assert!(node.ends_with(
+            "import { buildInfo as __buildInfo } from './debug-build-info.js';\n\
+             export const __wasmBodge = Object.freeze({ ...__buildInfo, environment: 'node', \
+             strategy: 'NodeFsSync', ready: Promise.resolve() });\n"
+        ));
+
+        // Async entrypoints hand out their own `ready`
+        let options = EntrypointOptions {
+            async_init: true,
+            ..Default::default()
+        };
+        let web = generate_esm_bundle_input(
+            Environment::Web,
+            "my_crate",
+            WasmVariant::Optimized,
+            &options,
+        )
+        .unwrap();
+        assert!(web.contains("environment: 'web', strategy: 'Base64Async', ready: ready })"));
+
+        let slim = generate_esm_entrypoint(
+            Environment::Slim,
+            "my_crate",
+            WasmVariant::Optimized,
+            &options,
+        );
+        assert!(!slim.contains("__wasmBodge"));
+
+        let cjs =
+            generate_cjs_entrypoint(Environment::Node, "my_crate", WasmVariant::Optimized).unwrap();
+        assert!(cjs.contains("require('./build-info.cjs')"));
+
+        assert_eq!(
+            generate_build_info(WasmVariant::Debug, Some("my-lib"), Some("1.2.3"), "abc"),
+            r#"{"variant":"debug","name":"my-lib","version":"1.2.3","wasmHash":"abc"}"#
+        );
+    }
+
+    #[test]
+    fn test_iife_environment() {
+        // The IIFE always bundles its own input, reporting its environment
+        let input = generate_esm_bundle_input(
+            Environment::Iife,
+            "my_crate",
+            WasmVariant::Optimized,
+            &EntrypointOptions::default(),
+        )
+        .unwrap();
+        assert!(input.contains("initSync({ module: bytes });"));
+        assert!(input.contains("environment: 'iife', strategy: 'Base64Embedded'"));
+        assert!(!Environment::all().contains(&Environment::Iife));
+
+        let (banner, footer) = generate_umd_wrapper("MyLib");
+        assert!(banner.contains("define.amd) define([], factory);"));
+        assert!(banner.contains("else root.MyLib = factory();"));
+        assert_eq!(footer, format!("return {UMD_EXPORTS};\n}});"));
+
+        assert_eq!(
+            generate_iife_types("MyLibDebug", "../index.js"),
+            "/** The exports of the script-tag bundle. */\n\
+             declare var MyLibDebug: typeof import(\"../index.js\");\n"
+        );
+        assert_eq!(
+            paths::iife_types(WasmVariant::Debug),
+            PathBuf::from("iife/debug.d.ts")
+        );
+    }
+
+    #[test]
+    fn test_wasm_source_override_imports_base64_lazily() {
+        let options = EntrypointOptions {
+            wasm_source_override: true,
+            ..Default::default()
+        };
+        for env in [Environment::Web, Environment::Worker] {
+            assert!(matches!(
+                options.init_strategy(env),
+                InitStrategy::Base64Overridable
+            ));
+        }
+        assert!(matches!(
+            options.init_strategy(Environment::Node),
+            InitStrategy::NodeFsSync
+        ));
+
+        let esm =
+            generate_esm_entrypoint(Environment::Web, "my_crate", WasmVariant::Debug, &options);
+        assert!(esm.contains("const source = wasmSource(__buildInfo);\n"));
+        assert!(esm.contains("import('./debug-wasm-base64.js')"));
+        assert!(!esm.contains("import { wasmBase64 }"));
+        assert!(esm.contains("\nawait ready;\n"));
+        assert!(esm.contains("strategy: 'Base64Overridable', ready: ready })"));
+
+        let bundle_input =
+            generate_esm_bundle_input(Environment::Web, "my_crate", WasmVariant::Debug, &options)
+                .unwrap();
+        assert!(!bundle_input.contains("await ready"));
+
+        let gzip = generate_esm_entrypoint(
+            Environment::Worker,
+            "my_crate",
+            WasmVariant::Optimized,
+            &EntrypointOptions {
+                compression: Some(WasmCompression::Gzip),
+                ..options
+            },
+        );
+        assert!(gzip.contains("import('./wasm-base64-gzip.js')"));
+        assert!(gzip.contains("gunzip(decodeBase64(m.wasmBase64Gzip))"));
+    }
+
+    #[test]
+    fn test_shared_node_instance_reexports_cjs() {
+        let options = EntrypointOptions {
+            shared_node_instance: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_esm_entrypoint(Environment::Node, "my_crate", WasmVariant::Debug, &options),
+            "export * from '../cjs/debug-node.cjs';\n"
+        );
+        // Only Node: Bun and the rest keep initializing their own instance
+        let bun = generate_esm_entrypoint(
+            Environment::Bun,
+            "my_crate",
+            WasmVariant::Optimized,
+            &options,
+        );
+        assert!(bun.contains("initSync"));
+
+        assert_eq!(
+            generate_esm_node_slim(WasmVariant::Optimized),
+            "import bindings from '../cjs/slim.cjs';\n\
+             export * from '../cjs/slim.cjs';\n\
+             export default bindings.default;\n"
+        );
+        // Node's ESM loader only detects the names of a re-exported require
+        let cjs =
+            generate_cjs_entrypoint(Environment::Node, "my_crate", WasmVariant::Optimized).unwrap();
+        assert!(
+            cjs.ends_with("module.exports = { ...require('./web-bindings.cjs'), __wasmBodge };\n")
+        );
+    }
+
     /// Fail if any generated entrypoint uses the deprecated
     /// positional-bytes form of `initSync`
     /// (wasm-bindgen deprecated it in 0.2.87 in favor of `initSync({ module: ... })`).
@@ -576,7 +1725,12 @@ mod tests {
             for variant in WasmVariant::all() {
                 generated.push((
                     format!("esm[{:?}, {:?}]", env, variant),
-                    generate_esm_entrypoint(*env, "my_crate", *variant),
+                    generate_esm_entrypoint(
+                        *env,
+                        "my_crate",
+                        *variant,
+                        &EntrypointOptions::default(),
+                    ),
                 ));
                 if let Some(cjs) = generate_cjs_entrypoint(*env, "my_crate", *variant) {
                     generated.push((format!("cjs[{:?}, {:?}]", env, variant), cjs));
@@ -0,0 +1,306 @@
+//! Minimal reader for the WebAssembly binary format.
+//!
+//! We only need to look at a handful of sections (imports and custom
+//! sections), so rather than pulling in a full parser this walks the section
+//! headers and decodes the few payloads we care about.
+
+use anyhow::{Context, Result};
+
+const WASM_MAGIC: &[u8; 4] = b"\0asm";
+const WASM_VERSION: &[u8; 4] = &[1, 0, 0, 0];
+const HEADER_LEN: usize = 8;
+
+/// Section id of custom sections.
+const CUSTOM_SECTION: u8 = 0;
+/// Section id of the import section.
+const IMPORT_SECTION: u8 = 2;
+/// Import kind of function imports.
+const FUNCTION_IMPORT: u8 = 0x00;
+/// Subsection id of function names in the `name` custom section.
+const FUNCTION_NAMES: u8 = 1;
+/// Section id of the code section.
+pub const CODE_SECTION: u8 = 10;
+
+/// A top-level section of a wasm module.
+#[derive(Debug, Clone, Copy)]
+pub struct Section<'a> {
+    pub id: u8,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Section<'a> {
+    /// The name of a custom section, or `None` for other sections.
+    pub fn custom_name(&self) -> Option<&'a str> {
+        self.split_custom().map(|(name, _)| name)
+    }
+
+    /// The contents of a custom section after its name.
+    pub fn custom_data(&self) -> Option<&'a [u8]> {
+        self.split_custom().map(|(_, data)| data)
+    }
+
+    fn split_custom(&self) -> Option<(&'a str, &'a [u8])> {
+        if self.id != CUSTOM_SECTION {
+            return None;
+        }
+        let mut reader = Reader::new(self.payload);
+        let len = reader.u32().ok()? as usize;
+        let name = std::str::from_utf8(reader.bytes(len).ok()?).ok()?;
+        Some((name, &self.payload[reader.pos..]))
+    }
+
+    /// Byte offset of this section's payload within `module`, the binary it
+    /// was read from.
+    pub fn payload_offset(&self, module: &[u8]) -> usize {
+        self.payload.as_ptr() as usize - module.as_ptr() as usize
+    }
+
+    /// Whether this is a DWARF custom section (`.debug_info`, `.debug_line`, ...).
+    pub fn is_dwarf(&self) -> bool {
+        self.custom_name()
+            .is_some_and(|name| name.starts_with(".debug_"))
+    }
+}
+
+/// An entry in the import section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    /// Import kind: 0x00 function, 0x01 table, 0x02 memory, 0x03 global, 0x04 tag
+    pub kind: u8,
+}
+
+/// Split a wasm binary into its top-level sections.
+pub fn sections(bytes: &[u8]) -> Result<Vec<Section<'_>>> {
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != WASM_MAGIC {
+        anyhow::bail!("Not a valid wasm binary");
+    }
+
+    let mut sections = Vec::new();
+    let mut reader = Reader::new(&bytes[HEADER_LEN..]);
+    while !reader.is_empty() {
+        let id = reader.byte()?;
+        let size = reader.u32()? as usize;
+        let payload = reader
+            .bytes(size)
+            .context("Section extends past end of file")?;
+        sections.push(Section { id, payload });
+    }
+    Ok(sections)
+}
+
+/// List the module's imports, in declaration order.
+pub fn imports(bytes: &[u8]) -> Result<Vec<Import>> {
+    let mut imports = Vec::new();
+    for section in sections(bytes)?.iter().filter(|s| s.id == IMPORT_SECTION) {
+        let mut reader = Reader::new(section.payload);
+        for _ in 0..reader.u32()? {
+            let module = reader.name()?;
+            let name = reader.name()?;
+            let kind = skip_import_desc(&mut reader)?;
+            imports.push(Import { module, name, kind });
+        }
+    }
+    Ok(imports)
+}
+
+/// Find a custom section by name.
+pub fn custom_section<'a>(sections: &[Section<'a>], name: &str) -> Option<&'a [u8]> {
+    sections
+        .iter()
+        .find(|s| s.custom_name() == Some(name))
+        .and_then(Section::custom_data)
+}
+
+/// Serialize sections into a wasm binary.
+pub fn encode<'a>(sections: impl IntoIterator<Item = Section<'a>>) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(WASM_MAGIC);
+    out.extend_from_slice(WASM_VERSION);
+    for section in sections {
+        out.push(section.id);
+        write_u32(&mut out, section.payload.len() as u32);
+        out.extend_from_slice(section.payload);
+    }
+    out
+}
+
+/// Build the payload of a custom section named `name`.
+pub fn custom_section_payload(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    write_name(&mut payload, name);
+    payload.extend_from_slice(data);
+    payload
+}
+
+/// Append a wasm `name` (length-prefixed UTF-8) to `out`.
+pub fn write_name(out: &mut Vec<u8>, name: &str) {
+    write_u32(out, name.len() as u32);
+    out.extend_from_slice(name.as_bytes());
+}
+
+/// Append an unsigned LEB128 to `out`.
+fn write_u32(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Number of imported functions, which come first in the function index space.
+pub fn imported_function_count(bytes: &[u8]) -> Result<u32> {
+    Ok(imports(bytes)?
+        .iter()
+        .filter(|i| i.kind == FUNCTION_IMPORT)
+        .count() as u32)
+}
+
+/// Byte ranges of each function body, relative to the start of the code
+/// section payload (the addresses DWARF uses), in definition order.
+pub fn function_bodies(sections: &[Section<'_>]) -> Result<Vec<std::ops::Range<u64>>> {
+    let Some(code) = sections.iter().find(|s| s.id == CODE_SECTION) else {
+        return Ok(Vec::new());
+    };
+    let mut reader = Reader::new(code.payload);
+    let count = reader.u32()?;
+    let mut bodies = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let size = reader.u32()? as usize;
+        let start = reader.pos as u64;
+        reader.bytes(size)?;
+        bodies.push(start..reader.pos as u64);
+    }
+    Ok(bodies)
+}
+
+/// Function names from the `name` section, by function index.
+pub fn function_names(sections: &[Section<'_>]) -> Result<Vec<(u32, String)>> {
+    let Some(data) = custom_section(sections, "name") else {
+        return Ok(Vec::new());
+    };
+    let mut names = Vec::new();
+    let mut reader = Reader::new(data);
+    while !reader.is_empty() {
+        let id = reader.byte()?;
+        let size = reader.u32()? as usize;
+        let payload = reader.bytes(size)?;
+        if id != FUNCTION_NAMES {
+            continue;
+        }
+        let mut sub = Reader::new(payload);
+        for _ in 0..sub.u32()? {
+            let index = sub.u32()?;
+            names.push((index, sub.name()?));
+        }
+    }
+    Ok(names)
+}
+
+/// Skip an import descriptor, returning its kind.
+fn skip_import_desc(reader: &mut Reader<'_>) -> Result<u8> {
+    let kind = reader.byte()?;
+    match kind {
+        // func: typeidx
+        0x00 => {
+            reader.u32()?;
+        }
+        // table: reftype + limits
+        0x01 => {
+            reader.byte()?;
+            skip_limits(reader)?;
+        }
+        // memory: limits
+        0x02 => skip_limits(reader)?,
+        // global: valtype + mutability
+        0x03 => {
+            reader.byte()?;
+            reader.byte()?;
+        }
+        // tag: attribute + typeidx
+        0x04 => {
+            reader.byte()?;
+            reader.u32()?;
+        }
+        other => anyhow::bail!("Unknown import kind 0x{other:02x}"),
+    }
+    Ok(kind)
+}
+
+fn skip_limits(reader: &mut Reader<'_>) -> Result<()> {
+    let flags = reader.byte()?;
+    // Memory64 limits are u64, but LEB128 decoding handles either width.
+    reader.u64()?;
+    if flags & 0x01 != 0 {
+        reader.u64()?;
+    }
+    Ok(())
+}
+
+/// Cursor over a byte slice that decodes wasm's primitive encodings.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .buf
+            .get(self.pos)
+            .context("Unexpected end of wasm data")?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.buf.len())
+            .context("Unexpected end of wasm data")?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Unsigned LEB128.
+    fn u64(&mut self) -> Result<u64> {
+        let mut result: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            result |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+            if shift >= 64 {
+                anyhow::bail!("LEB128 too long");
+            }
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        u32::try_from(self.u64()?).context("LEB128 value out of range for u32")
+    }
+
+    fn name(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).context("Name is not valid UTF-8")
+    }
+}
@@ -5,6 +5,7 @@ use std::{
     process::Command,
 };
 
+use super::symbols;
 use super::targets::{WasmBindgenTarget, WasmVariant};
 
 /// Build wasm and run wasm-bindgen for all targets. When `debug_profile`
@@ -16,6 +17,7 @@ pub fn build_wasm(
     release_profile: &str,
     debug_profile: Option<&str>,
     wasm_opt: bool,
+    symbol_map: Option<&Path>,
 ) -> Result<()> {
     // Resolve `target_dir` and `wasm_name` once: both are invariant across
     // the release and debug builds, and each call to `find_target_dir`
@@ -36,18 +38,127 @@ pub fn build_wasm(
         None => None,
     };
 
+    bindgen_variants(
+        &release_wasm,
+        debug_wasm.as_deref(),
+        output_dir,
+        wasm_opt,
+        symbol_map,
+    )
+}
+
+/// Run wasm-opt and wasm-bindgen on prebuilt wasm files, skipping cargo.
+///
+/// The inputs are copied into a staging directory first so that wasm-opt
+/// doesn't rewrite the caller's files in place, and so that wasm-bindgen
+/// names its output after `wasm_name` regardless of the input file names.
+pub fn bindgen_prebuilt(
+    release_wasm: &Path,
+    debug_wasm: Option<&Path>,
+    wasm_name: &str,
+    output_dir: &Path,
+    wasm_opt: bool,
+    symbol_map: Option<&Path>,
+) -> Result<()> {
+    let staging_dir = output_dir.join("input");
+    let stage = |src: &Path, variant: WasmVariant| -> Result<PathBuf> {
+        let dir = staging_dir.join(format!("wasm{}", variant.dir_suffix()));
+        std::fs::create_dir_all(&dir)?;
+        let dest = dir.join(format!("{wasm_name}.wasm"));
+        std::fs::copy(src, &dest).with_context(|| format!("Failed to copy wasm from {src:?}"))?;
+        Ok(dest)
+    };
+
+    let release_staged = stage(release_wasm, WasmVariant::Optimized)?;
+    let debug_staged = debug_wasm
+        .map(|wasm| stage(wasm, WasmVariant::Debug))
+        .transpose()?;
+
+    bindgen_variants(
+        &release_staged,
+        debug_staged.as_deref(),
+        output_dir,
+        wasm_opt,
+        symbol_map,
+    )?;
+
+    std::fs::remove_dir_all(&staging_dir).context("Failed to remove wasm staging directory")?;
+    Ok(())
+}
+
+/// Optimize the release wasm and run wasm-bindgen for every target of each
+/// variant. The debug variant only needs the web and bundler targets.
+///
+/// With `symbol_map`, the release wasm's function names and pre-opt source
+/// locations are written there (see [`symbols`]).
+fn bindgen_variants(
+    release_wasm: &Path,
+    debug_wasm: Option<&Path>,
+    output_dir: &Path,
+    wasm_opt: bool,
+    symbol_map: Option<&Path>,
+) -> Result<()> {
+    let locations = match symbol_map {
+        Some(_) => {
+            let bytes = std::fs::read(release_wasm).context("Failed to read release wasm")?;
+            Some(symbols::function_locations(&bytes)?)
+        }
+        None => None,
+    };
+    // Names only need preserving (and then stripping) when wasm-opt would
+    // otherwise drop them
+    let keep_names = wasm_opt && symbol_map.is_some();
+
     if wasm_opt {
         println!("  Running wasm-opt on release variant...");
-        run_wasm_opt(&release_wasm)?;
+        if keep_names {
+            // With -g wasm-opt would also try to keep the DWARF up to date,
+            // which disables optimizations. The locations are already read.
+            symbols::strip_sections(release_wasm, |s| s.is_dwarf())?;
+        }
+        run_wasm_opt(release_wasm, keep_names)?;
     }
 
     std::fs::create_dir_all(output_dir)?;
 
     for target in WasmBindgenTarget::all() {
-        run_wasm_bindgen(&release_wasm, output_dir, *target, WasmVariant::Optimized)?;
+        run_wasm_bindgen(release_wasm, output_dir, *target, WasmVariant::Optimized)?;
+    }
+
+    if let (Some(path), Some(locations)) = (symbol_map, &locations) {
+        let wasm_name = release_wasm
+            .file_stem()
+            .context("Release wasm has no file name")?
+            .to_string_lossy();
+        let bg_wasm = |target: WasmBindgenTarget| {
+            output_dir
+                .join(target.dir_name())
+                .join(format!("{wasm_name}_bg.wasm"))
+        };
+        // All targets share one module and import list, so function indices
+        // agree across them
+        let bytes = std::fs::read(bg_wasm(WasmBindgenTarget::Web))?;
+        let map = symbols::symbol_map(&bytes, locations)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(&map)?)
+            .with_context(|| format!("Failed to write symbol map {}", path.display()))?;
+        println!(
+            "  Wrote symbol map {} ({} functions, {} with source locations)",
+            path.display(),
+            map.functions.len(),
+            map.functions.values().filter(|s| s.file.is_some()).count()
+        );
+
+        if keep_names {
+            for target in WasmBindgenTarget::all() {
+                symbols::strip_sections(&bg_wasm(*target), |s| s.custom_name() == Some("name"))?;
+            }
+        }
     }
 
-    if let Some(debug_wasm) = debug_wasm.as_deref() {
+    if let Some(debug_wasm) = debug_wasm {
         for target in [WasmBindgenTarget::Web, WasmBindgenTarget::Bundler] {
             run_wasm_bindgen(debug_wasm, output_dir, target, WasmVariant::Debug)?;
         }
@@ -166,10 +277,17 @@ fn profile_dir_name(profile: &str) -> &str {
     }
 }
 
-fn run_wasm_opt(wasm_file: &Path) -> Result<()> {
+/// Run `wasm-opt -O4` in place. `keep_names` adds `-g` so the name section
+/// survives.
+fn run_wasm_opt(wasm_file: &Path, keep_names: bool) -> Result<()> {
     let wasm_path = wasm_file.to_string_lossy();
-    let status = Command::new("wasm-opt")
-        .args(["-O4", "--all-features", "-o", &wasm_path, &wasm_path])
+    let mut cmd = Command::new("wasm-opt");
+    cmd.args(["-O4", "--all-features"]);
+    if keep_names {
+        cmd.arg("-g");
+    }
+    let status = cmd
+        .args(["-o", &wasm_path, &wasm_path])
         .status()
         .context("Failed to run wasm-opt. Is it installed? (cargo install wasm-opt)")?;
 
@@ -0,0 +1,304 @@
+//! Import an existing wasm-pack `pkg/` directory as the Phase 1 output.
+//!
+//! wasm-pack runs wasm-bindgen for a single target. We can't re-run
+//! wasm-bindgen on its `_bg.wasm` (the wasm-bindgen custom sections have
+//! already been stripped), but for the web and bundler targets the `_bg.wasm`
+//! is identical and only the JS glue differs. So we copy the target wasm-pack
+//! built and generate the small amount of glue the other target needs.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::path::Path;
+
+use super::targets::WasmBindgenTarget;
+use super::wasm_binary;
+
+/// package.json fields that wasm-bodge generates itself, so wasm-pack's
+/// values for them are never carried over.
+const GENERATED_FIELDS: &[&str] = &[
+    "type",
+    "main",
+    "module",
+    "browser",
+    "types",
+    "typings",
+    "files",
+    "sideEffects",
+    "exports",
+];
+
+/// Find the wasm name of a wasm-pack output directory from its `_bg.wasm`.
+pub fn wasm_name(pkg_dir: &Path) -> Result<String> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(pkg_dir)
+        .with_context(|| format!("Failed to read wasm-pack directory {pkg_dir:?}"))?
+    {
+        let file_name = entry?.file_name();
+        if let Some(name) = file_name
+            .to_str()
+            .and_then(|name| name.strip_suffix("_bg.wasm"))
+        {
+            names.push(name.to_string());
+        }
+    }
+
+    match names.as_slice() {
+        [name] => Ok(name.clone()),
+        [] => anyhow::bail!("No *_bg.wasm file found in wasm-pack directory {pkg_dir:?}"),
+        _ => anyhow::bail!("Multiple *_bg.wasm files found in wasm-pack directory {pkg_dir:?}"),
+    }
+}
+
+/// Copy a wasm-pack `pkg/` directory into `output_dir` (the `wasm_bindgen/`
+/// directory) and generate the wasm-bindgen targets it is missing.
+pub fn import(pkg_dir: &Path, output_dir: &Path, wasm_name: &str) -> Result<()> {
+    let target = detect_target(pkg_dir, wasm_name)?;
+    println!("  Detected wasm-pack target '{target}'");
+
+    let src_dir = output_dir.join(target.dir_name());
+    copy_pkg(pkg_dir, &src_dir)?;
+
+    // The nodejs target is only used for its `.d.ts` (see finalize::copy_types)
+    let nodejs_dir = output_dir.join(WasmBindgenTarget::Nodejs.dir_name());
+    std::fs::create_dir_all(&nodejs_dir)?;
+    std::fs::copy(
+        src_dir.join(format!("{wasm_name}.d.ts")),
+        nodejs_dir.join(format!("{wasm_name}.d.ts")),
+    )
+    .context("Failed to copy wasm-pack type declarations")?;
+
+    match target {
+        WasmBindgenTarget::Bundler => {
+            println!("  Generating web target glue from bundler output...");
+            let web_dir = output_dir.join(WasmBindgenTarget::Web.dir_name());
+            copy_pkg(pkg_dir, &web_dir)?;
+            web_from_bundler(&web_dir, wasm_name)?;
+        }
+        WasmBindgenTarget::Web => {
+            println!("  Generating bundler target glue from web output...");
+            let bundler_dir = output_dir.join(WasmBindgenTarget::Bundler.dir_name());
+            bundler_from_web(&src_dir, &bundler_dir, wasm_name)?;
+        }
+        WasmBindgenTarget::Nodejs => unreachable!("detect_target never returns nodejs"),
+    }
+
+    Ok(())
+}
+
+/// Carry wasm-pack's package.json metadata (name, version, license, etc.)
+/// over to the template package.json, creating it if it doesn't exist.
+/// Fields already present in the template win.
+pub fn merge_package_json(pkg_dir: &Path, package_json_path: &Path) -> Result<()> {
+    let content = std::fs::read_to_string(pkg_dir.join("package.json"))
+        .context("Failed to read wasm-pack package.json")?;
+    let wasm_pack: Value =
+        serde_json::from_str(&content).context("Failed to parse wasm-pack package.json")?;
+    let wasm_pack = wasm_pack
+        .as_object()
+        .context("wasm-pack package.json must be an object")?;
+
+    let mut package: Value = if package_json_path.exists() {
+        let content =
+            std::fs::read_to_string(package_json_path).context("Failed to read package.json")?;
+        serde_json::from_str(&content).context("Failed to parse package.json")?
+    } else {
+        Value::Object(serde_json::Map::new())
+    };
+    let package_obj = package
+        .as_object_mut()
+        .context("package.json must be an object")?;
+
+    for (key, value) in wasm_pack {
+        if !GENERATED_FIELDS.contains(&key.as_str()) && !package_obj.contains_key(key) {
+            package_obj.insert(key.clone(), value.clone());
+        }
+    }
+
+    std::fs::write(package_json_path, serde_json::to_string_pretty(&package)?)?;
+    println!(
+        "  Merged wasm-pack package.json metadata into {}",
+        package_json_path.display()
+    );
+    Ok(())
+}
+
+/// wasm-pack's bundler output has a separate `_bg.js`; the web output has a
+/// single module exporting `initSync`. Other targets can't be imported.
+fn detect_target(pkg_dir: &Path, wasm_name: &str) -> Result<WasmBindgenTarget> {
+    if pkg_dir.join(format!("{wasm_name}_bg.js")).exists() {
+        return Ok(WasmBindgenTarget::Bundler);
+    }
+
+    let js = std::fs::read_to_string(pkg_dir.join(format!("{wasm_name}.js")))
+        .context("Failed to read wasm-pack JS output")?;
+    if js.contains("function initSync(") {
+        return Ok(WasmBindgenTarget::Web);
+    }
+
+    anyhow::bail!(
+        "Unsupported wasm-pack output in {pkg_dir:?}: only `wasm-pack build --target web` \
+         and `--target bundler` output can be imported"
+    )
+}
+
+/// Copy everything wasm-pack emitted except its package.json and README.
+fn copy_pkg(src: &Path, dest: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        if matches!(
+            name.to_str(),
+            Some("package.json" | "README.md" | ".gitignore")
+        ) {
+            continue;
+        }
+        if path.is_dir() {
+            // wasm-bindgen inline JS snippets (`snippets/`)
+            copy_pkg(&path, &dest.join(&name))?;
+        } else {
+            std::fs::copy(&path, dest.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Turn a copy of the bundler output into a web target module.
+///
+/// The bundler `_bg.js` already holds all the glue plus a module-level
+/// `wasm` binding and `__wbg_set_wasm`. It's missing the `initSync` and
+/// default async init functions, which instantiate the wasm with the glue
+/// module itself as the `./{name}_bg.js` import namespace.
+fn web_from_bundler(web_dir: &Path, wasm_name: &str) -> Result<()> {
+    let bg_js = std::fs::read_to_string(web_dir.join(format!("{wasm_name}_bg.js")))
+        .context("Failed to read wasm-pack _bg.js")?;
+    if !bg_js.contains("export function __wbg_set_wasm(") {
+        anyhow::bail!("wasm-pack _bg.js does not export __wbg_set_wasm");
+    }
+
+    let web_js = format!(
+        r#"{bg_js}
+import * as __wbg_self from './{name}.js';
+
+function __wbg_get_imports() {{
+    return {{ './{name}_bg.js': __wbg_self }};
+}}
+
+function __wbg_finalize_init(instance) {{
+    __wbg_set_wasm(instance.exports);
+    if (typeof wasm.__wbindgen_start === 'function') wasm.__wbindgen_start();
+    return wasm;
+}}
+
+function initSync(module) {{
+    if (wasm !== undefined) return wasm;
+    if (module !== undefined && Object.getPrototypeOf(module) === Object.prototype) {{
+        ({{ module }} = module);
+    }}
+    if (!(module instanceof WebAssembly.Module)) {{
+        module = new WebAssembly.Module(module);
+    }}
+    return __wbg_finalize_init(new WebAssembly.Instance(module, __wbg_get_imports()));
+}}
+
+async function __wbg_init(module_or_path) {{
+    if (wasm !== undefined) return wasm;
+    if (module_or_path !== undefined && Object.getPrototypeOf(module_or_path) === Object.prototype) {{
+        ({{ module_or_path }} = module_or_path);
+    }}
+    if (module_or_path === undefined) {{
+        module_or_path = new URL('{name}_bg.wasm', import.meta.url);
+    }}
+    if (typeof module_or_path === 'string' || (typeof Request === 'function' && module_or_path instanceof Request) || (typeof URL === 'function' && module_or_path instanceof URL)) {{
+        module_or_path = fetch(module_or_path);
+    }}
+    let source = await module_or_path;
+    if (typeof Response === 'function' && source instanceof Response) {{
+        source = await source.arrayBuffer();
+    }}
+    const result = await WebAssembly.instantiate(source, __wbg_get_imports());
+    return __wbg_finalize_init(result instanceof WebAssembly.Instance ? result : result.instance);
+}}
+
+export {{ initSync, __wbg_init as default }};
+"#,
+        bg_js = bg_js,
+        name = wasm_name,
+    );
+    std::fs::write(web_dir.join(format!("{wasm_name}.js")), web_js)?;
+    std::fs::remove_file(web_dir.join(format!("{wasm_name}_bg.js")))?;
+
+    let dts_path = web_dir.join(format!("{wasm_name}.d.ts"));
+    let mut dts = std::fs::read_to_string(&dts_path).context("Failed to read wasm-pack .d.ts")?;
+    dts.push_str(
+        r#"
+export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
+
+export type SyncInitInput = BufferSource | WebAssembly.Module;
+
+export function initSync(module: { module: SyncInitInput } | SyncInitInput): WebAssembly.Exports;
+
+export default function __wbg_init (module_or_path?: { module_or_path: InitInput | Promise<InitInput> } | InitInput | Promise<InitInput>): Promise<WebAssembly.Exports>;
+"#,
+    );
+    std::fs::write(&dts_path, dts)?;
+    Ok(())
+}
+
+/// Generate the bundler target's `_bg.js` and `_bg.wasm` from web output.
+///
+/// The bundler shim entrypoint only needs `_bg.js` to provide the wasm's
+/// imports and `__wbg_set_wasm`. The web module builds those imports in
+/// `__wbg_get_imports`, so we export that and re-export each import the wasm
+/// declares under its own name. This only works when the wasm imports from
+/// `./{name}_bg.js` (newer wasm-bindgen); older versions import from `wbg`,
+/// which a bundler can't resolve.
+fn bundler_from_web(web_dir: &Path, bundler_dir: &Path, wasm_name: &str) -> Result<()> {
+    let wasm_file = format!("{wasm_name}_bg.wasm");
+    let import_module = format!("./{wasm_name}_bg.js");
+    let wasm_bytes =
+        std::fs::read(web_dir.join(&wasm_file)).context("Failed to read wasm-pack _bg.wasm")?;
+
+    let ident = Regex::new(r"^[A-Za-z_$][A-Za-z0-9_$]*$")?;
+    let mut import_names = Vec::new();
+    for import in wasm_binary::imports(&wasm_bytes)? {
+        if import.module != import_module {
+            anyhow::bail!(
+                "wasm imports from module `{}`, but bundler glue can only be generated for \
+                 wasm importing from `{import_module}`. Rebuild with \
+                 `wasm-pack build --target bundler` or a newer wasm-bindgen.",
+                import.module
+            );
+        }
+        if !ident.is_match(&import.name) {
+            anyhow::bail!("wasm import `{}` is not a valid JS identifier", import.name);
+        }
+        import_names.push(import.name);
+    }
+
+    let web_js_path = web_dir.join(format!("{wasm_name}.js"));
+    let mut web_js =
+        std::fs::read_to_string(&web_js_path).context("Failed to read wasm-pack JS output")?;
+    if !web_js.contains("function __wbg_get_imports(") {
+        anyhow::bail!("wasm-pack web output has no __wbg_get_imports function");
+    }
+    web_js.push_str("\nexport { __wbg_get_imports };\n");
+    std::fs::write(&web_js_path, web_js)?;
+
+    let web_js_rel = format!("../web/{wasm_name}.js");
+    let mut bg_js = format!(
+        "import {{ __wbg_get_imports }} from '{web_js_rel}';\n\
+         export {{ __wbg_set_wasm }} from '{web_js_rel}';\n\
+         const imports = __wbg_get_imports()['{import_module}'];\n"
+    );
+    for name in &import_names {
+        bg_js.push_str(&format!("export const {name} = imports.{name};\n"));
+    }
+
+    std::fs::create_dir_all(bundler_dir)?;
+    std::fs::write(bundler_dir.join(format!("{wasm_name}_bg.js")), bg_js)?;
+    std::fs::copy(web_dir.join(&wasm_file), bundler_dir.join(&wasm_file))?;
+    Ok(())
+}
@@ -0,0 +1,457 @@
+//! The `check-types` command: resolving each export of a package the way
+//! TypeScript does under each `moduleResolution` mode, in the spirit of
+//! are-the-types-wrong, and reporting exports whose declarations are
+//! missing, don't resolve, or describe the wrong module format.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::build::declarations::Format;
+
+/// A quoted relative specifier in a declaration file.
+static RELATIVE_SPECIFIER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"["'](\.\.?/[^"'\s]*)["']"#).unwrap());
+
+/// A TypeScript `moduleResolution` mode, with the importing file's format
+/// for node16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Node10,
+    Node16Cjs,
+    Node16Esm,
+    Bundler,
+}
+
+impl Mode {
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Node10 => "node10",
+            Mode::Node16Cjs => "node16 (from CJS)",
+            Mode::Node16Esm => "node16 (from ESM)",
+            Mode::Bundler => "bundler",
+        }
+    }
+
+    /// Export conditions the mode matches, besides `default` (and `types`
+    /// when resolving declarations).
+    fn conditions(self) -> &'static [&'static str] {
+        match self {
+            Mode::Node10 => &[],
+            Mode::Node16Cjs => &["require", "node"],
+            Mode::Node16Esm => &["import", "node"],
+            Mode::Bundler => &["import"],
+        }
+    }
+
+    fn is_node16(self) -> bool {
+        matches!(self, Mode::Node16Cjs | Mode::Node16Esm)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub subpath: String,
+    pub mode: Mode,
+    pub kind: ProblemKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProblemKind {
+    /// The JS resolves, but no declarations do
+    Untyped { js: String },
+    /// A file the package points at doesn't exist
+    MissingFile { path: String },
+    /// Declarations of one format describing JS of the other
+    Masquerading {
+        types: String,
+        types_format: Format,
+        js: String,
+        js_format: Format,
+    },
+    /// `require` resolves to an ES module
+    CjsResolvesToEsm { js: String },
+    /// A relative import in the declarations doesn't resolve
+    InternalResolution {
+        file: String,
+        specifier: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]: ", self.subpath, self.mode.name())?;
+        match &self.kind {
+            ProblemKind::Untyped { js } => write!(f, "no declarations for {js}"),
+            ProblemKind::MissingFile { path } => write!(f, "{path} doesn't exist"),
+            ProblemKind::Masquerading {
+                types,
+                types_format,
+                js,
+                js_format,
+            } => write!(
+                f,
+                "{types} describes {types_format} but {js} is {js_format} \
+                 (masquerading as {types_format})"
+            ),
+            ProblemKind::CjsResolvesToEsm { js } => {
+                write!(f, "require resolves to the ES module {js}")
+            }
+            ProblemKind::InternalResolution {
+                file,
+                specifier,
+                reason,
+            } => write!(f, "import {specifier:?} in {file}: {reason}"),
+        }
+    }
+}
+
+/// Check the package in `package_dir` and print the problems, failing if
+/// there are any.
+pub fn run(package_dir: &Path) -> Result<()> {
+    let problems = check(package_dir)?;
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "Found {} problem(s) with the package's types",
+            problems.len()
+        );
+    }
+    println!("The package's types resolve under node10, node16 and bundler");
+    Ok(())
+}
+
+/// Resolve the package root under node10, which ignores `exports`, and each
+/// conditional export under node16 (from CJS and ESM) and bundler. Exports
+/// that are a single path (or one per `development`/`production` mode), like
+/// `./wasm` and `./iife`, are assets or scripts rather than typed modules and
+/// aren't checked.
+pub fn check(package_dir: &Path) -> Result<Vec<Problem>> {
+    let package_json = package_dir.join("package.json");
+    let content = std::fs::read_to_string(&package_json)
+        .with_context(|| format!("Failed to read {}", package_json.display()))?;
+    let package: Value = serde_json::from_str(&content).context("Failed to parse package.json")?;
+    let checker = Checker {
+        dir: package_dir,
+        package_type: if package["type"] == "module" {
+            Format::Esm
+        } else {
+            Format::Cjs
+        },
+        problems: Vec::new(),
+    };
+    checker.check(&package)
+}
+
+struct Checker<'a> {
+    dir: &'a Path,
+    /// Format of `.js` and `.d.ts` files
+    package_type: Format,
+    problems: Vec<Problem>,
+}
+
+impl Checker<'_> {
+    fn check(mut self, package: &Value) -> Result<Vec<Problem>> {
+        let main = package["main"].as_str().unwrap_or("./index.js");
+        let types = package["types"].as_str().or(package["typings"].as_str());
+        self.check_resolution(".", Mode::Node10, main, types);
+
+        let exports = match &package["exports"] {
+            Value::Object(exports) if exports.keys().all(|k| k.starts_with('.')) => exports.clone(),
+            Value::Null => serde_json::Map::new(),
+            // Conditions without subpaths apply to the root
+            root => serde_json::Map::from_iter([(".".to_string(), root.clone())]),
+        };
+        for (subpath, target) in &exports {
+            if is_plain_path(target) || subpath.contains('*') {
+                continue;
+            }
+            for mode in [Mode::Node16Cjs, Mode::Node16Esm, Mode::Bundler] {
+                let Some(js) = resolve_target(target, mode.conditions()) else {
+                    // Not exported to this mode at all, like an ESM-only
+                    // export under node16 from CJS
+                    continue;
+                };
+                let mut conditions = vec!["types"];
+                conditions.extend(mode.conditions());
+                let types = resolve_target(target, &conditions).filter(|t| is_declaration(t));
+                self.check_resolution(subpath, mode, js, types);
+            }
+        }
+        Ok(self.problems)
+    }
+
+    /// Check an export that resolves to `js`, and to the declarations
+    /// `types` if a `types` condition (or field) matched.
+    fn check_resolution(&mut self, subpath: &str, mode: Mode, js: &str, types: Option<&str>) {
+        let js_path = self.dir.join(js);
+        let types = match types {
+            Some(types) => types.to_string(),
+            // TypeScript looks for declarations next to the JS
+            None => match sibling_declaration(js) {
+                Some(types) if self.dir.join(&types).is_file() => types,
+                _ => {
+                    self.report(subpath, mode, ProblemKind::Untyped { js: js.to_string() });
+                    return;
+                }
+            },
+        };
+        let types_path = self.dir.join(&types);
+        for (path, file) in [(&js_path, js), (&types_path, types.as_str())] {
+            if !path.is_file() {
+                self.report(
+                    subpath,
+                    mode,
+                    ProblemKind::MissingFile {
+                        path: file.to_string(),
+                    },
+                );
+                return;
+            }
+        }
+
+        if mode.is_node16() {
+            let js_format = self.format(&js_path);
+            let types_format = self.format(&types_path);
+            if mode == Mode::Node16Cjs && js_format == Format::Esm {
+                self.report(
+                    subpath,
+                    mode,
+                    ProblemKind::CjsResolvesToEsm { js: js.to_string() },
+                );
+            }
+            if types_format != js_format {
+                self.report(
+                    subpath,
+                    mode,
+                    ProblemKind::Masquerading {
+                        types: types.clone(),
+                        types_format,
+                        js: js.to_string(),
+                        js_format,
+                    },
+                );
+            }
+        }
+
+        let mut visited = HashSet::new();
+        self.check_imports(subpath, mode, &types_path, &mut visited);
+    }
+
+    /// Check that the relative imports of the declarations at `path`, and of
+    /// the declarations they import, resolve under `mode`.
+    fn check_imports(
+        &mut self,
+        subpath: &str,
+        mode: Mode,
+        path: &Path,
+        visited: &mut HashSet<PathBuf>,
+    ) {
+        if !visited.insert(path.to_path_buf()) {
+            return;
+        }
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return;
+        };
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let format = self.format(path);
+        for c in RELATIVE_SPECIFIER.captures_iter(&content) {
+            let spec = &c[1];
+            let resolved = resolve_relative(dir, spec, mode.is_node16() && format == Format::Esm);
+            let resolved = resolved.and_then(|resolved| {
+                if mode.is_node16()
+                    && format == Format::Cjs
+                    && self.format(&resolved) == Format::Esm
+                {
+                    Err("CommonJS declarations can't import an ES module".to_string())
+                } else {
+                    Ok(resolved)
+                }
+            });
+            match resolved {
+                Ok(resolved) => self.check_imports(subpath, mode, &resolved, visited),
+                Err(reason) => {
+                    let file = self.display(path);
+                    self.report(
+                        subpath,
+                        mode,
+                        ProblemKind::InternalResolution {
+                            file,
+                            specifier: spec.to_string(),
+                            reason,
+                        },
+                    )
+                }
+            }
+        }
+    }
+
+    fn report(&mut self, subpath: &str, mode: Mode, kind: ProblemKind) {
+        self.problems.push(Problem {
+            subpath: subpath.to_string(),
+            mode,
+            kind,
+        });
+    }
+
+    /// The format TypeScript (and Node) give a file: by extension, with
+    /// `.js` and `.d.ts` following the package's `type`.
+    fn format(&self, path: &Path) -> Format {
+        let name = path.to_string_lossy();
+        if [".mjs", ".mts"].iter().any(|e| name.ends_with(e)) {
+            Format::Esm
+        } else if [".cjs", ".cts"].iter().any(|e| name.ends_with(e)) {
+            Format::Cjs
+        } else {
+            self.package_type
+        }
+    }
+
+    fn display(&self, path: &Path) -> String {
+        let relative = pathdiff::diff_paths(path, self.dir).unwrap_or(path.to_path_buf());
+        format!("./{}", relative.display())
+    }
+}
+
+/// Resolve an export's `target` under `conditions` like Node: the first
+/// matching condition (or `default`) in order, recursing into nested
+/// conditions and falling through those where nothing matches.
+fn resolve_target<'a>(target: &'a Value, conditions: &[&str]) -> Option<&'a str> {
+    match target {
+        Value::String(path) => Some(path),
+        Value::Object(map) => map
+            .iter()
+            .filter(|(condition, _)| {
+                *condition == "default" || conditions.contains(&condition.as_str())
+            })
+            .find_map(|(_, target)| resolve_target(target, conditions)),
+        Value::Array(targets) => targets.iter().find_map(|t| resolve_target(t, conditions)),
+        _ => None,
+    }
+}
+
+/// Whether an export target is a path, picked by mode at most.
+fn is_plain_path(target: &Value) -> bool {
+    match target {
+        Value::String(_) => true,
+        Value::Object(map) => map.iter().all(|(condition, target)| {
+            ["development", "production", "default"].contains(&condition.as_str())
+                && is_plain_path(target)
+        }),
+        _ => false,
+    }
+}
+
+fn is_declaration(path: &str) -> bool {
+    [".d.ts", ".d.mts", ".d.cts"]
+        .iter()
+        .any(|e| path.ends_with(e))
+}
+
+/// The declaration file TypeScript looks for next to a JS file.
+fn sibling_declaration(js: &str) -> Option<String> {
+    [(".mjs", ".d.mts"), (".cjs", ".d.cts"), (".js", ".d.ts")]
+        .iter()
+        .find_map(|(ext, decl)| Some(format!("{}{decl}", js.strip_suffix(ext)?)))
+}
+
+/// Resolve the relative import `spec` of declarations in `dir` to a
+/// declaration file. Extensionless imports aren't resolved from ES module
+/// declarations under node16, like Node doesn't resolve them at runtime.
+fn resolve_relative(dir: &Path, spec: &str, needs_extension: bool) -> Result<PathBuf, String> {
+    let name = spec.rsplit('/').next().unwrap_or(spec);
+    let candidates = if let Some(declaration) = sibling_declaration(spec) {
+        vec![declaration]
+    } else if name.contains('.') {
+        vec![spec.to_string()]
+    } else if needs_extension {
+        return Err("relative imports of ES module declarations need a file extension".into());
+    } else {
+        vec![
+            format!("{spec}.d.ts"),
+            format!("{}/index.d.ts", spec.trim_end_matches('/')),
+        ]
+    };
+    candidates
+        .iter()
+        .map(|candidate| dir.join(candidate))
+        .find(|path| path.is_file())
+        .ok_or_else(|| "no declarations found".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_types() {
+        let dir = std::env::temp_dir().join("wasm-bodge-test-check-types");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("dist")).unwrap();
+        for file in [
+            "index.js",
+            "index.cjs",
+            "index.d.ts",
+            "index.d.cts",
+            "esm-only.js",
+            "untyped.cjs",
+        ] {
+            std::fs::write(dir.join("dist").join(file), "").unwrap();
+        }
+        std::fs::write(
+            dir.join("dist/index.d.mts"),
+            "export * from './inner.mjs';\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("dist/inner.d.mts"), "export * from './gone';\n").unwrap();
+        let package = serde_json::json!({
+            "type": "module",
+            "main": "./dist/index.cjs",
+            "types": "./dist/index.d.ts",
+            "exports": {
+                // One .d.ts for both formats
+                ".": {
+                    "types": "./dist/index.d.ts",
+                    "import": "./dist/index.js",
+                    "require": "./dist/index.cjs"
+                },
+                "./split": {
+                    "import": { "types": "./dist/index.d.mts", "default": "./dist/index.js" },
+                    "require": { "types": "./dist/index.d.cts", "default": "./dist/index.cjs" }
+                },
+                "./esm-only": { "import": "./dist/esm-only.js" },
+                "./untyped": { "require": "./dist/untyped.cjs" },
+                "./wasm": "./dist/index.wasm"
+            }
+        });
+        std::fs::write(dir.join("package.json"), package.to_string()).unwrap();
+
+        let problems: Vec<_> = check(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                ". [node16 (from CJS)]: ./dist/index.d.ts describes ESM but \
+                 ./dist/index.cjs is CJS (masquerading as ESM)",
+                "./split [node16 (from ESM)]: import \"./gone\" in ./dist/inner.d.mts: \
+                 relative imports of ES module declarations need a file extension",
+                "./split [bundler]: import \"./gone\" in ./dist/inner.d.mts: \
+                 no declarations found",
+                "./esm-only [node16 (from ESM)]: no declarations for ./dist/esm-only.js",
+                "./esm-only [bundler]: no declarations for ./dist/esm-only.js",
+                "./untyped [node16 (from CJS)]: no declarations for ./dist/untyped.cjs",
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
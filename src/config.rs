@@ -1,4 +1,9 @@
-use std::path::PathBuf;
+use anyhow::Context;
+use heck::ToPascalCase;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::build::targets::WasmVariant;
 
 /// Configuration for the build command
 #[derive(Debug)]
@@ -9,5 +14,256 @@ pub struct BuildConfig {
     pub release_profile: String,
     pub debug_profile: Option<String>,
     pub wasm_bindgen_tar: Option<PathBuf>,
+    pub wasm: Option<PathBuf>,
+    pub wasm_pack_dir: Option<PathBuf>,
+    pub debug_wasm: Option<PathBuf>,
+    pub wasm_name: Option<String>,
     pub wasm_opt: bool,
+    pub compress_wasm: Option<WasmCompression>,
+    pub async_init: AsyncInit,
+    pub async_init_threshold: u64,
+    pub asset_url_conditions: Vec<AssetUrlCondition>,
+    pub split_debug_info: Option<PathBuf>,
+    pub debug_info_url: Option<String>,
+    pub wasm_source_map: bool,
+    pub source_map_url: Option<String>,
+    pub source_map_sources_content: bool,
+    pub symbol_map: Option<PathBuf>,
+    pub js_source_maps: JsSourceMaps,
+    pub minify: Minify,
+    pub shared_node_instance: bool,
+    pub wasm_source_override: bool,
+    pub development_conditions: bool,
+    pub file: FileConfig,
+}
+
+impl BuildConfig {
+    /// Whether the build has a debug variant, from --debug-profile or
+    /// --debug-wasm.
+    pub fn has_debug_variant(&self) -> bool {
+        self.debug_profile.is_some() || self.debug_wasm.is_some()
+    }
+}
+
+/// Settings read from the `wasm-bodge.toml` config file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    /// Replaces the default export condition mapping, in order
+    pub conditions: Option<Vec<ConditionConfig>>,
+    /// Extra subpath exports for hand-written modules
+    #[serde(default)]
+    pub subpaths: Vec<SubpathConfig>,
+    /// Hand-written module that the generated entrypoints re-export
+    /// instead of the raw bindings
+    pub facade: Option<FacadeConfig>,
+    /// Settings of the script-tag bundle
+    #[serde(default)]
+    pub iife: IifeConfig,
+}
+
+/// One `[[conditions]]` entry: an export condition and the environments
+/// whose entrypoints it resolves to.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionConfig {
+    pub condition: String,
+    /// Environment for `import` (not needed for the `require` condition)
+    pub esm: Option<String>,
+    /// Environment for `require` (not needed for the `import` condition)
+    pub cjs: Option<String>,
+}
+
+/// One `[[subpaths]]` entry: a hand-written JS or TS module exposed as an
+/// extra subpath export.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubpathConfig {
+    /// Export subpath, e.g. "./helpers"
+    pub path: String,
+    /// Module source, relative to the config file
+    pub source: PathBuf,
+    /// Declarations to ship instead of generating them with tsc, relative
+    /// to the config file
+    pub types: Option<PathBuf>,
+}
+
+/// The `[facade]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FacadeConfig {
+    /// Module source, relative to the config file. It imports the bindings
+    /// from `wasm-bodge:bindings`.
+    pub source: PathBuf,
+    /// Declarations to ship instead of generating them with tsc, relative
+    /// to the config file
+    pub types: Option<PathBuf>,
+}
+
+/// The `[iife]` table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IifeConfig {
+    /// Global the bundle assigns its exports to, with a `Debug` suffix for
+    /// the debug variant [default: the crate name in PascalCase]
+    pub global_name: Option<String>,
+    /// Wrapper around the bundle
+    #[serde(default)]
+    pub format: IifeFormat,
+    /// esbuild `--target` for the bundle, e.g. "es2017" or "chrome64,safari12"
+    pub target: Option<String>,
+}
+
+impl IifeConfig {
+    /// The global `variant`'s bundle assigns its exports to.
+    pub fn global_name(&self, crate_name: &str, variant: WasmVariant) -> String {
+        let name = match &self.global_name {
+            Some(name) => name.clone(),
+            None => crate_name.to_pascal_case(),
+        };
+        if variant.is_debug() {
+            format!("{name}Debug")
+        } else {
+            name
+        }
+    }
+}
+
+/// How the script-tag bundle exposes its exports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IifeFormat {
+    /// Assigns them to the global
+    #[default]
+    Iife,
+    /// Defines an AMD module or sets `module.exports` if there is a loader,
+    /// and the global otherwise
+    Umd,
+}
+
+impl FileConfig {
+    /// Load `path`, or `<crate_path>/wasm-bodge.toml` if it exists, or the
+    /// defaults.
+    pub fn load(path: Option<&Path>, crate_path: &Path) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let default = crate_path.join("wasm-bodge.toml");
+                if !default.exists() {
+                    return Ok(Self::default());
+                }
+                default
+            }
+        };
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut config: Self = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        for subpath in &mut config.subpaths {
+            subpath.source = dir.join(&subpath.source);
+            subpath.types = subpath.types.as_ref().map(|types| dir.join(types));
+        }
+        if let Some(facade) = &mut config.facade {
+            facade.source = dir.join(&facade.source);
+            facade.types = facade.types.as_ref().map(|types| dir.join(types));
+        }
+        if let Some(name) = &config.iife.global_name {
+            let mut chars = name.chars();
+            let valid = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+            if !valid {
+                anyhow::bail!(
+                    "[iife] global_name {name:?} in {} is not a JavaScript identifier",
+                    path.display()
+                );
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Compression applied to the wasm embedded in the base64 entrypoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WasmCompression {
+    /// gzip, decoded at init by a bundled inflater
+    Gzip,
+}
+
+/// When the base64-embedded entrypoints initialize the wasm asynchronously.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AsyncInit {
+    /// Async when the variant's wasm is larger than --async-init-threshold
+    Auto,
+    /// Always initialize with top-level await
+    Always,
+    /// Always initialize synchronously with initSync
+    Never,
+}
+
+impl AsyncInit {
+    /// Whether a wasm binary of `wasm_size` bytes should initialize asynchronously.
+    pub fn applies_to(self, wasm_size: u64, threshold: u64) -> bool {
+        match self {
+            Self::Auto => wasm_size > threshold,
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Export conditions that can resolve to the asset-URL entrypoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AssetUrlCondition {
+    /// The "browser" condition (bundlers), instead of the bundler shim
+    Browser,
+    /// The "import" fallback (native ESM, CDNs), instead of base64 web
+    Import,
+}
+
+/// Which variants' esbuild bundles get source maps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum JsSourceMaps {
+    /// The debug variant and minified bundles
+    Debug,
+    /// Both variants
+    All,
+    /// Neither variant
+    None,
+}
+
+impl JsSourceMaps {
+    /// Whether `variant`'s bundles get source maps, given whether they're
+    /// minified.
+    pub fn applies_to(self, variant: WasmVariant, minified: bool) -> bool {
+        match self {
+            Self::Debug => variant.is_debug() || minified,
+            Self::All => true,
+            Self::None => false,
+        }
+    }
+}
+
+/// Which variants' esbuild bundles are minified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Minify {
+    /// Only the optimized variant
+    Optimized,
+    /// Both variants
+    All,
+    /// Neither variant
+    None,
+}
+
+impl Minify {
+    pub fn applies_to(self, variant: WasmVariant) -> bool {
+        match self {
+            Self::Optimized => !variant.is_debug(),
+            Self::All => true,
+            Self::None => false,
+        }
+    }
 }
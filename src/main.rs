@@ -3,7 +3,9 @@ use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 mod build;
+mod check_types;
 mod config;
+mod symbolicate;
 
 #[derive(Parser)]
 #[command(name = "wasm-bodge")]
@@ -14,6 +16,7 @@ struct Cli {
 }
 
 #[derive(Subcommand)]
+#[expect(clippy::large_enum_variant)]
 enum Commands {
     /// Build an npm package from a wasm-bindgen Rust crate
     Build {
@@ -44,9 +47,135 @@ enum Commands {
         #[arg(long)]
         wasm_bindgen_tar: Option<PathBuf>,
 
+        /// Use a prebuilt `.wasm` file instead of building the crate with
+        /// cargo. wasm-opt and wasm-bindgen still run on the supplied file.
+        #[arg(long, conflicts_with = "wasm_bindgen_tar")]
+        wasm: Option<PathBuf>,
+
+        /// Use an existing wasm-pack output directory (`--target web` or
+        /// `--target bundler`) instead of building the crate. Its
+        /// package.json metadata is merged into --package-json.
+        #[arg(long, conflicts_with_all = ["wasm_bindgen_tar", "wasm", "debug_profile"])]
+        wasm_pack_dir: Option<PathBuf>,
+
+        /// Prebuilt `.wasm` file for the debug variant. Like --debug-profile,
+        /// passing this builds a parallel `/debug` subpath export.
+        #[arg(long, requires = "wasm", conflicts_with = "debug_profile")]
+        debug_wasm: Option<PathBuf>,
+
+        /// Name of the wasm-bindgen output files when using --wasm
+        /// [default: file stem of --wasm]
+        #[arg(long, requires = "wasm")]
+        wasm_name: Option<String>,
+
         /// Disable wasm-opt optimization
         #[arg(long, default_value_t = false)]
         no_wasm_opt: bool,
+
+        /// Compress the wasm embedded in the base64 entrypoints (web, IIFE
+        /// and their CJS bundles)
+        #[arg(long, value_enum)]
+        compress_wasm: Option<config::WasmCompression>,
+
+        /// Initialize the base64-embedded entrypoints asynchronously (top-level
+        /// await in ESM, a `ready` promise in CJS and IIFE) instead of with
+        /// initSync, which browsers refuse for large modules on the main thread
+        #[arg(long, value_enum, default_value_t = config::AsyncInit::Auto)]
+        async_init: config::AsyncInit,
+
+        /// Wasm size in bytes above which `--async-init auto` switches a
+        /// variant to async initialization
+        #[arg(long, default_value_t = 4 * 1024 * 1024)]
+        async_init_threshold: u64,
+
+        /// Export conditions whose ESM entrypoint loads the wasm from a
+        /// `new URL(..., import.meta.url)` asset instead of a wasm ESM import
+        /// or embedded base64 (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        asset_url_conditions: Vec<config::AssetUrlCondition>,
+
+        /// Move the debug variant's DWARF into `<DIR>/<name>.debug.wasm`,
+        /// referenced from the packaged debug wasm by an
+        /// `external_debug_info` section. DIR may be outside the package.
+        #[arg(long, value_name = "DIR")]
+        split_debug_info: Option<PathBuf>,
+
+        /// URL recorded in the `external_debug_info` section
+        /// [default: the debug file's name, relative to the wasm]
+        #[arg(long, requires = "split_debug_info")]
+        debug_info_url: Option<String>,
+
+        /// Generate a wasm source map from the debug variant's DWARF, for
+        /// devtools that can't read DWARF (Firefox, Safari)
+        #[arg(long, default_value_t = false)]
+        wasm_source_map: bool,
+
+        /// URL recorded in the debug wasm's `sourceMappingURL` section
+        /// [default: the map's file name, relative to the wasm]
+        #[arg(long, requires = "wasm_source_map")]
+        source_map_url: Option<String>,
+
+        /// Embed the Rust sources in the wasm source map
+        #[arg(long, default_value_t = false, requires = "wasm_source_map")]
+        source_map_sources_content: bool,
+
+        /// Write a symbol map of the release wasm (function names and
+        /// pre-opt source locations) to PATH, for `wasm-bodge symbolicate`.
+        /// PATH may be outside the package.
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["wasm_bindgen_tar", "wasm_pack_dir"])]
+        symbol_map: Option<PathBuf>,
+
+        /// Which variants get external source maps for their esbuild bundles
+        /// (`cjs/*.cjs` bundles and the IIFE), mapping back to the
+        /// wasm-bindgen glue
+        #[arg(long, value_enum, default_value_t = config::JsSourceMaps::Debug)]
+        js_source_maps: config::JsSourceMaps,
+
+        /// Which variants' esbuild bundles (`cjs/*.cjs` bundles and the IIFE)
+        /// are minified
+        #[arg(long, value_enum, default_value_t = config::Minify::Optimized)]
+        minify: config::Minify,
+
+        /// Make the Node ESM entrypoints re-export the CommonJS ones, so an
+        /// app that imports the package and a dependency that requires it
+        /// share one wasm instance
+        #[arg(long, default_value_t = false)]
+        shared_node_instance: bool,
+
+        /// Let pages provide the wasm of the base64 entrypoints (web, worker
+        /// and IIFE) at runtime through `globalThis.__wasmBodgeWasm`, which
+        /// makes them initialize asynchronously
+        #[arg(long, default_value_t = false)]
+        wasm_source_override: bool,
+
+        /// Add `development` and `production` conditions to the optimized
+        /// subpaths, resolving `development` (bundler dev servers) to the
+        /// debug variant
+        #[arg(long, default_value_t = false)]
+        development_conditions: bool,
+
+        /// Config file with the export condition mapping
+        /// [default: <crate-path>/wasm-bodge.toml, if it exists]
+        #[arg(long, value_name = "PATH")]
+        config: Option<PathBuf>,
+    },
+    /// Rewrite `wasm-function[N]` frames in a stack trace from the release
+    /// wasm using a symbol map written by `build --symbol-map`
+    Symbolicate {
+        /// Symbol map written by `build --symbol-map`
+        #[arg(long)]
+        symbol_map: PathBuf,
+
+        /// File containing the stack trace [default: stdin]
+        trace: Option<PathBuf>,
+    },
+    /// Check that every export's declarations resolve, under TypeScript's
+    /// node10, node16 and bundler module resolution, and describe the same
+    /// module format as its JS
+    CheckTypes {
+        /// Directory of the package.json to check
+        #[arg(long, default_value = ".")]
+        package_dir: PathBuf,
     },
 }
 
@@ -61,8 +190,29 @@ fn main() -> Result<()> {
             release_profile,
             debug_profile,
             wasm_bindgen_tar,
+            wasm,
+            wasm_pack_dir,
+            debug_wasm,
+            wasm_name,
             no_wasm_opt,
+            compress_wasm,
+            async_init,
+            async_init_threshold,
+            asset_url_conditions,
+            split_debug_info,
+            debug_info_url,
+            wasm_source_map,
+            source_map_url,
+            source_map_sources_content,
+            symbol_map,
+            js_source_maps,
+            minify,
+            shared_node_instance,
+            wasm_source_override,
+            development_conditions,
+            config,
         } => {
+            let file = config::FileConfig::load(config.as_deref(), &crate_path)?;
             let config = config::BuildConfig {
                 crate_path,
                 package_json,
@@ -70,10 +220,36 @@ fn main() -> Result<()> {
                 release_profile,
                 debug_profile,
                 wasm_bindgen_tar,
+                wasm,
+                wasm_pack_dir,
+                debug_wasm,
+                wasm_name,
                 wasm_opt: !no_wasm_opt,
+                compress_wasm,
+                async_init,
+                async_init_threshold,
+                asset_url_conditions,
+                split_debug_info,
+                debug_info_url,
+                wasm_source_map,
+                source_map_url,
+                source_map_sources_content,
+                symbol_map,
+                js_source_maps,
+                minify,
+                shared_node_instance,
+                wasm_source_override,
+                development_conditions,
+                file,
             };
             build::run(config)?;
         }
+        Commands::Symbolicate { symbol_map, trace } => {
+            symbolicate::run(&symbol_map, trace.as_deref())?;
+        }
+        Commands::CheckTypes { package_dir } => {
+            check_types::run(&package_dir)?;
+        }
     }
 
     Ok(())
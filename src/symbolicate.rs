@@ -0,0 +1,150 @@
+//! The `symbolicate` command: rewriting `wasm-function[N]` frames in a stack
+//! trace from the release wasm using the symbol map written by
+//! `build --symbol-map`.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+/// Current version of the symbol map format.
+pub const SYMBOL_MAP_VERSION: u32 = 1;
+
+/// Function names and source locations of the release wasm, by function index.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SymbolMap {
+    pub version: u32,
+    pub functions: BTreeMap<u32, Symbol>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Symbol {
+    /// Demangled name, without the hash suffix
+    pub name: String,
+    /// Where the function was declared, from the pre-opt DWARF
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+impl SymbolMap {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read symbol map {}", path.display()))?;
+        let map: Self = serde_json::from_str(&content).context("Failed to parse symbol map")?;
+        if map.version != SYMBOL_MAP_VERSION {
+            anyhow::bail!(
+                "Unsupported symbol map version {} (expected {SYMBOL_MAP_VERSION})",
+                map.version
+            );
+        }
+        Ok(map)
+    }
+}
+
+/// Read a stack trace from `trace` (or stdin) and print it symbolicated.
+pub fn run(symbol_map: &Path, trace: Option<&Path>) -> Result<()> {
+    let map = SymbolMap::load(symbol_map)?;
+    let input = match trace {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?,
+        None => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input)?;
+            input
+        }
+    };
+    print!("{}", symbolicate(&input, &map));
+    Ok(())
+}
+
+/// Rewrite every line naming a `wasm-function[N]` found in `map` as
+/// `at name (file:line) [wasm-function[N]:0x...]`, keeping its indentation.
+///
+/// Handles the Chrome (`at wasm-function[N]:0x...`), Firefox
+/// (`wasm-function[N]@url:wasm-function[N]:0x...`) and Safari
+/// (`<?>.wasm-function[N]@[wasm code]`) formats. Other lines are unchanged.
+pub fn symbolicate(trace: &str, map: &SymbolMap) -> String {
+    let frame = Regex::new(r"wasm-function\[(\d+)\](:0x[0-9a-fA-F]+)?").unwrap();
+
+    let mut out = String::with_capacity(trace.len());
+    for line in trace.split_inclusive('\n') {
+        let mut captures = frame.captures_iter(line).peekable();
+        let symbol = captures
+            .peek()
+            .and_then(|c| c[1].parse().ok())
+            .and_then(|index: u32| Some((index, map.functions.get(&index)?)));
+        let Some((index, symbol)) = symbol else {
+            out.push_str(line);
+            continue;
+        };
+        let offset = captures.find_map(|c| c.get(2)).map_or("", |m| m.as_str());
+
+        let indent = &line[..line.len() - line.trim_start().len()];
+        out.push_str(indent);
+        out.push_str("at ");
+        out.push_str(&symbol.name);
+        if let Some(file) = &symbol.file {
+            out.push_str(&format!(" ({file}"));
+            if let Some(line) = symbol.line {
+                out.push_str(&format!(":{line}"));
+            }
+            out.push(')');
+        }
+        out.push_str(&format!(" [wasm-function[{index}]{offset}]"));
+        if line.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbolicate_browser_formats() {
+        let mut map = SymbolMap {
+            version: SYMBOL_MAP_VERSION,
+            ..Default::default()
+        };
+        map.functions.insert(
+            12,
+            Symbol {
+                name: "test_wasm_lib::greet".to_string(),
+                file: Some("/src/lib.rs".to_string()),
+                line: Some(11),
+            },
+        );
+        map.functions.insert(
+            3,
+            Symbol {
+                name: "core::panicking::panic".to_string(),
+                file: None,
+                line: None,
+            },
+        );
+
+        let chrome = "RuntimeError: unreachable\n    at wasm-function[3]:0x1f2\n    at wasm-function[12]:0x4a0\n    at greet (index.js:10:5)\n";
+        assert_eq!(
+            symbolicate(chrome, &map),
+            "RuntimeError: unreachable\n    at core::panicking::panic [wasm-function[3]:0x1f2]\n    at test_wasm_lib::greet (/src/lib.rs:11) [wasm-function[12]:0x4a0]\n    at greet (index.js:10:5)\n"
+        );
+
+        let firefox = "wasm-function[12]@http://localhost/lib_bg.wasm:wasm-function[12]:0x4a0";
+        assert_eq!(
+            symbolicate(firefox, &map),
+            "at test_wasm_lib::greet (/src/lib.rs:11) [wasm-function[12]:0x4a0]"
+        );
+
+        let safari = "<?>.wasm-function[12]@[wasm code]\n<?>.wasm-function[99]@[wasm code]\n";
+        assert_eq!(
+            symbolicate(safari, &map),
+            "at test_wasm_lib::greet (/src/lib.rs:11) [wasm-function[12]]\n<?>.wasm-function[99]@[wasm code]\n"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+// Compares the base64 decoders used by the base64-embedded entrypoints
+// against the `atob` + `charCodeAt` baseline they replaced. Run by
+// `test_base64_decode_benchmark` in tests/packaging.rs, or directly with
+// `node tests/benchmarks/base64_decode.mjs`.
+
+import { decodeBase64 } from '../../src/build/js/decode_base64.js';
+
+const SIZE = 4 * 1024 * 1024;
+const RUNS = 5;
+
+const bytes = new Uint8Array(SIZE);
+for (let i = 0; i < SIZE; i++) bytes[i] = (i * 2654435761) >>> 24;
+const base64 = Buffer.from(bytes).toString('base64');
+
+function time(name, decode) {
+    let best = Infinity;
+    for (let run = 0; run < RUNS; run++) {
+        const start = performance.now();
+        const out = decode(base64);
+        best = Math.min(best, performance.now() - start);
+        if (Buffer.compare(Buffer.from(out), Buffer.from(bytes)) !== 0) {
+            throw new Error(`${name} decoded incorrectly`);
+        }
+    }
+    console.log(`${name.padEnd(24)} ${best.toFixed(1)}ms`);
+}
+
+// Run `decodeBase64` with `Uint8Array.fromBase64` hidden, and optionally
+// `Buffer`, to force its slower paths.
+function fallback({ hideBuffer }) {
+    return (input) => {
+        const { fromBase64 } = Uint8Array;
+        const { Buffer: buffer } = globalThis;
+        delete Uint8Array.fromBase64;
+        if (hideBuffer) globalThis.Buffer = undefined;
+        try {
+            return decodeBase64(input);
+        } finally {
+            if (fromBase64) Uint8Array.fromBase64 = fromBase64;
+            globalThis.Buffer = buffer;
+        }
+    };
+}
+
+// Every input length modulo 3, to cover the padding paths
+for (let len = 0; len < 8; len++) {
+    const slice = bytes.subarray(0, len);
+    const out = fallback({ hideBuffer: true })(Buffer.from(slice).toString('base64'));
+    if (Buffer.compare(Buffer.from(out), Buffer.from(slice)) !== 0) {
+        throw new Error(`lookup table decoded ${len} bytes incorrectly`);
+    }
+}
+
+console.log(`Decoding ${SIZE} bytes (best of ${RUNS}):`);
+time('atob + charCodeAt', (s) => Uint8Array.from(atob(s), (c) => c.charCodeAt(0)));
+if (typeof Uint8Array.fromBase64 === 'function') {
+    time('Uint8Array.fromBase64', decodeBase64);
+}
+time('Buffer.from', fallback({ hideBuffer: false }));
+time('lookup table', fallback({ hideBuffer: true }));
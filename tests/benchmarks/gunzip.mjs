@@ -0,0 +1,98 @@
+// Round-trips real zlib output through the bundled gzip decoder used by the
+// compressed base64 entrypoints, then times it against the native
+// `DecompressionStream`. Run by `test_gunzip` in tests/packaging.rs, or
+// directly with `node tests/benchmarks/gunzip.mjs`.
+//
+// The streams mix stored blocks (level 0) with fixed and dynamic Huffman
+// blocks, and sync-flush between chunks, so blocks end on byte boundaries.
+
+import { constants, createGzip, gzipSync } from 'node:zlib';
+import { gunzip, gunzipSync } from '../../src/build/js/gunzip.js';
+
+const STREAMS = 300;
+
+// Deterministic xorshift, so failures reproduce
+let seed = 0x9e3779b9;
+function random(n) {
+    seed ^= seed << 13;
+    seed ^= seed >>> 17;
+    seed ^= seed << 5;
+    return (seed >>> 0) % n;
+}
+
+function payload() {
+    const bytes = new Uint8Array(random(64 * 1024) + 1);
+    const text = new TextEncoder().encode('wasm-bodge stored and compressed blocks ');
+    for (let i = 0; i < bytes.length; i++) {
+        bytes[i] = random(2) ? text[i % text.length] : random(256);
+    }
+    return bytes;
+}
+
+// Gzip `bytes` in random chunks, each followed by a sync flush, switching
+// the compression level between chunks
+function gzipFlushed(bytes) {
+    return new Promise((resolve, reject) => {
+        const gzip = createGzip({ level: random(10) });
+        const chunks = [];
+        gzip.on('data', (chunk) => chunks.push(chunk));
+        gzip.on('end', () => resolve(new Uint8Array(Buffer.concat(chunks))));
+        gzip.on('error', reject);
+
+        let pos = 0;
+        const next = () => {
+            if (pos >= bytes.length) {
+                gzip.end();
+                return;
+            }
+            const end = Math.min(bytes.length, pos + random(8192) + 1);
+            gzip.write(bytes.subarray(pos, end));
+            pos = end;
+            gzip.flush(constants.Z_SYNC_FLUSH, () => {
+                gzip.params(random(10), constants.Z_DEFAULT_STRATEGY, next);
+            });
+        };
+        next();
+    });
+}
+
+function check(name, out, bytes) {
+    if (Buffer.compare(Buffer.from(out), Buffer.from(bytes)) !== 0) {
+        throw new Error(`${name} inflated a stream incorrectly`);
+    }
+}
+
+const { DecompressionStream: nativeStream } = globalThis;
+for (let i = 0; i < STREAMS; i++) {
+    const bytes = payload();
+    const gzipped = await gzipFlushed(bytes);
+    check('gunzipSync', gunzipSync(gzipped), bytes);
+    check('gunzip', await gunzip(gzipped), bytes);
+    globalThis.DecompressionStream = undefined;
+    try {
+        check('gunzip (fallback)', await gunzip(gzipped), bytes);
+    } finally {
+        globalThis.DecompressionStream = nativeStream;
+    }
+}
+console.log(`${STREAMS} sync-flushed streams round-trip`);
+
+const SIZE = 4 * 1024 * 1024;
+const RUNS = 5;
+const large = new Uint8Array(SIZE);
+for (let i = 0; i < SIZE; i++) large[i] = (i * 2654435761) >>> 28;
+const gzipped = new Uint8Array(gzipSync(large));
+
+async function time(name, inflate) {
+    let best = Infinity;
+    for (let run = 0; run < RUNS; run++) {
+        const start = performance.now();
+        const out = await inflate(gzipped);
+        best = Math.min(best, performance.now() - start);
+        check(name, out, large);
+    }
+    console.log(`${name.padEnd(24)} ${best.toFixed(1)}ms`);
+}
+
+await time('gunzipSync', gunzipSync);
+if (typeof nativeStream === 'function') await time('DecompressionStream', gunzip);
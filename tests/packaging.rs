@@ -16,11 +16,12 @@ use std::process::{Child, Command, Stdio};
 use std::sync::OnceLock;
 
 static BUILD_RESULT: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+static SHARED_INSTANCE_BUILD_RESULT: OnceLock<Result<PathBuf, String>> = OnceLock::new();
 static PUPPETEER_INSTALLED: OnceLock<Result<(), String>> = OnceLock::new();
 
 /// Build the test fixture once and return the path to the built package
 fn get_test_package() -> Result<PathBuf> {
-    let result = BUILD_RESULT.get_or_init(build_test_package);
+    let result = BUILD_RESULT.get_or_init(|| build_test_package("wasm-bodge-test-build", &[]));
 
     match result {
         Ok(path) => Ok(path.clone()),
@@ -28,6 +29,21 @@ fn get_test_package() -> Result<PathBuf> {
     }
 }
 
+/// Like `get_test_package`, built with `--shared-node-instance`
+fn get_shared_instance_test_package() -> Result<PathBuf> {
+    let result = SHARED_INSTANCE_BUILD_RESULT.get_or_init(|| {
+        build_test_package(
+            "wasm-bodge-test-build-shared-instance",
+            &["--shared-node-instance"],
+        )
+    });
+
+    match result {
+        Ok(path) => Ok(path.clone()),
+        Err(e) => anyhow::bail!("Shared-instance test package build failed: {}", e),
+    }
+}
+
 const TEST_PACKAGE_JSON: &str = r#"{
   "name": "test-wasm-lib",
   "version": "0.1.0",
@@ -51,9 +67,9 @@ fn copy_fixture_crate(dest: &Path) -> Result<(), String> {
     Ok(())
 }
 
-fn build_test_package() -> Result<PathBuf, String> {
+fn build_test_package(dir_name: &str, extra_args: &[&str]) -> Result<PathBuf, String> {
     // Copy fixture to a temp directory so we don't modify the repo
-    let crate_path = std::env::temp_dir().join("wasm-bodge-test-build");
+    let crate_path = std::env::temp_dir().join(dir_name);
     let _ = std::fs::remove_dir_all(&crate_path);
     copy_fixture_crate(&crate_path)?;
 
@@ -65,12 +81,9 @@ fn build_test_package() -> Result<PathBuf, String> {
 
     // Build with a debug variant so the debug-symbol and ./debug export
     // tests can run against the same cached build.
-    let output = run_wasm_bodge_build(
-        &crate_path,
-        &package_json,
-        &out_dir,
-        &["--debug-profile", "wasm-debug"],
-    );
+    let mut args = vec!["--debug-profile", "wasm-debug"];
+    args.extend(extra_args);
+    let output = run_wasm_bodge_build(&crate_path, &package_json, &out_dir, &args);
 
     if !output.status.success() {
         return Err(format!(
@@ -130,6 +143,8 @@ enum BrowserTestKind {
     ViteBuild,
     /// Serve static files from test dir (for IIFE)
     StaticRoot,
+    /// Serve the template and its node_modules as-is (native browser ESM)
+    StaticTemplate,
 }
 
 /// Determine the browser test kind for a template, if any
@@ -142,14 +157,26 @@ fn browser_test_kind(template_name: &str) -> Option<BrowserTestKind> {
         Some(BrowserTestKind::ViteBuild)
     } else if template_name == "iife_script" {
         Some(BrowserTestKind::StaticRoot)
+    } else if template_name.starts_with("native_esm_") {
+        Some(BrowserTestKind::StaticTemplate)
     } else {
         None
     }
 }
 
+/// Whether a template tests `--shared-node-instance`, where `import` and
+/// `require` in Node share one wasm instance
+fn uses_shared_node_instance(template_name: &str) -> bool {
+    template_name.ends_with("_shared_instance")
+}
+
 /// Run a test for the given template directory name
 fn run_test(template_name: &str) -> Result<()> {
-    let package_dir = get_test_package()?;
+    let package_dir = if uses_shared_node_instance(template_name) {
+        get_shared_instance_test_package()?
+    } else {
+        get_test_package()?
+    };
 
     let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     let template_dir = project_root.join("tests/templates").join(template_name);
@@ -294,6 +321,9 @@ fn run_browser_test(project_root: &Path, test_dir: &Path, kind: BrowserTestKind)
             std::fs::copy(&iife_src, &iife_dest).context("Failed to copy IIFE bundle")?;
             run_static_server_test(project_root, test_dir, "/index.html")?;
         }
+        BrowserTestKind::StaticTemplate => {
+            run_static_server_test(project_root, test_dir, "/index.html")?;
+        }
         BrowserTestKind::ViteDev => {
             run_vite_dev_test(project_root, test_dir)?;
         }
@@ -627,6 +657,31 @@ fn test_workerd_slim() {
     run_test("workerd_slim").unwrap();
 }
 
+#[test]
+fn test_deno_fullfat() {
+    run_test("deno_fullfat").unwrap();
+}
+
+#[test]
+fn test_bun_fullfat() {
+    run_test("bun_fullfat").unwrap();
+}
+
+#[test]
+fn test_bun_cjs_compiled() {
+    run_test("bun_cjs_compiled").unwrap();
+}
+
+#[test]
+fn test_edge_light_fullfat() {
+    run_test("edge_light_fullfat").unwrap();
+}
+
+#[test]
+fn test_worker_fullfat() {
+    run_test("worker_fullfat").unwrap();
+}
+
 #[test]
 fn test_node_esm_cross_init() {
     run_test("node_esm_cross_init").unwrap();
@@ -637,11 +692,36 @@ fn test_node_cjs_cross_init() {
     run_test("node_cjs_cross_init").unwrap();
 }
 
+#[test]
+fn test_node_esm_shared_instance() {
+    run_test("node_esm_shared_instance").unwrap();
+}
+
+#[test]
+fn test_node_cjs_shared_instance() {
+    run_test("node_cjs_shared_instance").unwrap();
+}
+
 #[test]
 fn test_iife_script() {
     run_test("iife_script").unwrap();
 }
 
+#[test]
+fn test_webpack_esm_asset_url() {
+    run_test("webpack_esm_asset_url").unwrap();
+}
+
+#[test]
+fn test_vite_build_asset_url() {
+    run_test("vite_build_asset_url").unwrap();
+}
+
+#[test]
+fn test_native_esm_asset_url() {
+    run_test("native_esm_asset_url").unwrap();
+}
+
 /// Parse wasm custom sections and check whether any section name begins with
 /// `.debug_` (DWARF debug info). Returns an error if the file isn't a valid
 /// wasm binary.
@@ -727,6 +807,82 @@ fn test_debug_symbols() {
     );
 }
 
+/// Each export's `types` describes what its entrypoint exports: slim adds
+/// the web target's init functions, asset-url adds `ready`. The `import`
+/// and `require` sides get `.d.mts` and `.d.cts` declarations, which the
+/// `check-types` command resolves without problems.
+#[test]
+fn test_export_types() {
+    let package_dir = get_test_package().unwrap();
+    let package: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(package_dir.join("package.json")).unwrap())
+            .unwrap();
+    let exports = &package["exports"];
+    for (export, types) in [
+        (".", "./dist/index"),
+        ("./debug", "./dist/index"),
+        ("./slim", "./dist/slim"),
+        ("./debug/slim", "./dist/debug-slim"),
+        ("./wasm-base64", "./dist/wasm-base64"),
+        ("./debug/wasm-base64", "./dist/wasm-base64"),
+    ] {
+        assert_eq!(
+            exports[export]["import"]["types"],
+            format!("{types}.d.mts"),
+            "{export}"
+        );
+        assert_eq!(
+            exports[export]["require"]["types"],
+            format!("{types}.d.cts"),
+            "{export}"
+        );
+    }
+    assert_eq!(
+        exports["."]["node"]["require"],
+        serde_json::json!({
+            "types": "./dist/index.d.cts",
+            "default": "./dist/cjs/node.cjs"
+        })
+    );
+    assert_eq!(
+        exports["./asset-url"]["import"]["types"],
+        "./dist/asset-url.d.mts"
+    );
+    assert_eq!(package["types"], "./dist/index.d.ts");
+
+    let read = |path: &str| std::fs::read_to_string(package_dir.join("dist").join(path)).unwrap();
+    assert!(read("index.d.ts").contains("export function greet"));
+    assert!(!read("index.d.ts").contains("initSync"));
+    assert!(read("index.d.ts").contains("export declare const __wasmBodge"));
+    // The worker entrypoint, mapped by default, exports `ready`
+    assert!(read("index.d.ts").contains("export declare const ready: Promise<void>;"));
+    assert_eq!(read("index.d.cts"), read("index.d.ts"));
+    for slim in ["slim.d.mts", "debug-slim.d.cts"] {
+        let slim = read(slim);
+        assert!(slim.contains("export function greet"));
+        assert!(slim.contains("export function initSync"));
+        assert!(slim.contains("export default function __wbg_init"));
+    }
+    assert_eq!(
+        read("asset-url.d.mts"),
+        "export * from './index.mjs';\nexport declare const ready: Promise<void>;\n"
+    );
+    assert!(read("wasm-base64.d.cts").contains("wasmBase64: string"));
+
+    let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let output = Command::new("cargo")
+        .args(["run", "--release", "--", "check-types", "--package-dir"])
+        .arg(&package_dir)
+        .current_dir(&project_root)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "check-types failed:\n{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
 #[test]
 fn test_node_esm_debug() {
     run_test("node_esm_debug").unwrap();
@@ -843,6 +999,52 @@ fn write_test_package_json(path: &Path) {
     std::fs::write(path, TEST_PACKAGE_JSON).expect("Failed to write package.json");
 }
 
+/// A copy of the fixture crate, built by [`build_fixture`].
+struct Fixture {
+    crate_path: PathBuf,
+    package_json: PathBuf,
+    out_dir: PathBuf,
+    stdout: String,
+}
+
+/// Temp directory [`build_fixture`] copies the fixture crate to.
+fn fixture_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("wasm-bodge-test-{name}"))
+}
+
+/// Copy the fixture crate to [`fixture_path`], write `files` (paths relative
+/// to the crate, e.g. `wasm-bodge.toml`) into it and build it with `args`
+/// into `dist/`, failing the test if the build fails.
+fn build_fixture(name: &str, files: &[(&str, &str)], args: &[&str]) -> Fixture {
+    let crate_path = fixture_path(name);
+    let _ = std::fs::remove_dir_all(&crate_path);
+    copy_fixture_crate(&crate_path).unwrap();
+    for (path, content) in files {
+        let path = crate_path.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    let package_json = crate_path.join("package.json");
+    write_test_package_json(&package_json);
+    let out_dir = crate_path.join("dist");
+
+    let output = run_wasm_bodge_build(&crate_path, &package_json, &out_dir, args);
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "wasm-bodge build failed:\nstdout: {stdout}\nstderr: {stderr}",
+    );
+
+    Fixture {
+        crate_path,
+        package_json,
+        out_dir,
+        stdout,
+    }
+}
+
 /// With `[profile.wasm-debug]` declared, the debug variant is compiled by a
 /// dedicated cargo build (not copied from the release wasm).
 #[test]
@@ -890,6 +1092,295 @@ fn test_two_profile_debug_build() {
     let _ = std::fs::remove_dir_all(&crate_path);
 }
 
+/// `--split-debug-info` moves the debug variant's DWARF into an external file
+/// outside the package and leaves a reference to it in the packaged wasm.
+#[test]
+fn test_split_debug_info() {
+    let debug_dir = fixture_path("split-debug-info").join("debug-info");
+    let Fixture {
+        crate_path,
+        out_dir,
+        ..
+    } = build_fixture(
+        "split-debug-info",
+        &[],
+        &[
+            "--debug-profile",
+            "wasm-debug",
+            "--split-debug-info",
+            debug_dir.to_str().unwrap(),
+        ],
+    );
+
+    let debug_file = debug_dir.join("test_wasm_lib.debug.wasm");
+    assert!(
+        has_debug_sections(&debug_file).unwrap(),
+        "external debug file should have DWARF"
+    );
+
+    let debug_wasm = out_dir.join("test-wasm-lib-debug.wasm");
+    assert!(
+        !has_debug_sections(&debug_wasm).unwrap(),
+        "packaged debug wasm should have no DWARF"
+    );
+    let bytes = std::fs::read(&debug_wasm).unwrap();
+    assert!(
+        bytes
+            .windows(b"external_debug_info".len())
+            .any(|w| w == b"external_debug_info"),
+        "packaged debug wasm should reference the external debug file"
+    );
+    assert!(
+        bytes.len() < std::fs::metadata(&debug_file).unwrap().len() as usize,
+        "packaged debug wasm should be smaller than the debug file"
+    );
+
+    // The stripped wasm still runs
+    let output = Command::new("node")
+        .args([
+            "--input-type=module",
+            "-e",
+            "const m = await import(process.argv[1]);\n\
+             if (m.greet('split') !== 'Hello, split!') throw new Error('greet failed');",
+            out_dir.join("esm/debug-node.js").to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to run node");
+    assert!(
+        output.status.success(),
+        "debug-node.js failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// `--wasm-source-map` turns the debug variant's DWARF into a source map next
+/// to the wasm the debug entrypoints load, with the crate's sources embedded.
+#[test]
+fn test_wasm_source_map() {
+    let Fixture {
+        crate_path,
+        out_dir,
+        ..
+    } = build_fixture(
+        "wasm-source-map",
+        &[],
+        &[
+            "--debug-profile",
+            "wasm-debug",
+            "--wasm-source-map",
+            "--source-map-sources-content",
+        ],
+    );
+
+    for dir in ["web-debug", "bundler-debug"] {
+        let dir = out_dir.join("wasm_bindgen").join(dir);
+        let map: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.join("test_wasm_lib_bg.wasm.map")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(map["version"], 3);
+        assert!(!map["mappings"].as_str().unwrap().is_empty());
+
+        let sources = map["sources"].as_array().unwrap();
+        let lib_rs = sources
+            .iter()
+            .position(|s| s.as_str().unwrap().ends_with("src/lib.rs"))
+            .expect("source map should reference the crate's lib.rs");
+        let content = map["sourcesContent"][lib_rs].as_str().unwrap();
+        assert!(content.contains("pub fn greet"));
+
+        let wasm = std::fs::read(dir.join("test_wasm_lib_bg.wasm")).unwrap();
+        assert!(
+            wasm.windows(b"sourceMappingURL".len())
+                .any(|w| w == b"sourceMappingURL"),
+            "debug wasm should carry a sourceMappingURL section"
+        );
+    }
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// `--symbol-map` writes the release wasm's function names outside the
+/// package, and `symbolicate` uses it to name `wasm-function[N]` frames.
+#[test]
+fn test_symbol_map() {
+    let symbol_map = fixture_path("symbol-map").join("symbols/test_wasm_lib.symbols.json");
+    let Fixture {
+        crate_path,
+        out_dir,
+        ..
+    } = build_fixture(
+        "symbol-map",
+        &[],
+        &["--symbol-map", symbol_map.to_str().unwrap()],
+    );
+
+    let map: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&symbol_map).unwrap()).unwrap();
+    let (index, greet) = map["functions"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .find(|(_, f)| f["name"] == "greet")
+        .expect("symbol map should name greet");
+    assert!(greet["file"].as_str().unwrap().ends_with("src/lib.rs"));
+
+    // The packaged wasm doesn't carry the names
+    let wasm = std::fs::read(out_dir.join("test-wasm-lib.wasm")).unwrap();
+    assert!(
+        !wasm.windows(b"\x04name".len()).any(|w| w == b"\x04name"),
+        "release wasm should not have a name section"
+    );
+
+    let trace = crate_path.join("trace.txt");
+    std::fs::write(
+        &trace,
+        format!("RuntimeError: unreachable\n    at wasm-function[{index}]:0x1f2\n"),
+    )
+    .unwrap();
+    let output = Command::new("cargo")
+        .args(["run", "--release", "--", "symbolicate", "--symbol-map"])
+        .arg(&symbol_map)
+        .arg(&trace)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("Failed to run wasm-bodge symbolicate");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(
+        stdout.contains("at greet (") && stdout.contains("src/lib.rs:"),
+        "unexpected symbolicated trace: {stdout}"
+    );
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// esbuild bundles get linked source maps by default for the debug variant
+/// and the minified optimized variant, mapping back to the wasm-bindgen glue.
+#[test]
+fn test_js_source_maps() {
+    let Fixture {
+        crate_path,
+        package_json,
+        out_dir,
+        stdout,
+        ..
+    } = build_fixture("js-source-maps", &[], &["--debug-profile", "wasm-debug"]);
+
+    for bundle in [
+        "cjs/debug-web.cjs",
+        "cjs/debug-web-bindings.cjs",
+        "iife/debug.js",
+    ] {
+        let js = std::fs::read_to_string(out_dir.join(bundle)).unwrap();
+        assert!(
+            js.contains("//# sourceMappingURL="),
+            "{bundle} should link its source map"
+        );
+        let map: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(out_dir.join(format!("{bundle}.map"))).unwrap(),
+        )
+        .unwrap();
+        assert!(
+            map["sources"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|s| s.as_str().unwrap().contains("wasm_bindgen/web-debug/")),
+            "{bundle}.map should map back to the wasm-bindgen glue"
+        );
+    }
+
+    // The debug ESM entrypoints get identity maps embedding the glue
+    let js = std::fs::read_to_string(out_dir.join("esm/debug-node.js")).unwrap();
+    assert!(js.ends_with("//# sourceMappingURL=debug-node.js.map\n"));
+    let map: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(out_dir.join("esm/debug-node.js.map")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(map["sources"][0], "debug-node.js");
+    assert_eq!(
+        map["sources"][1],
+        "../wasm_bindgen/web-debug/test_wasm_lib.js"
+    );
+    assert!(map["sourcesContent"][1].as_str().is_some());
+
+    // The optimized bundles are minified, each with its map. The reported
+    // size leaves out the wasm embedded in web.cjs and the IIFE.
+    let report = stdout
+        .lines()
+        .find(|l| l.contains("Minified optimized bundles: "))
+        .expect("the build should report the minified bundles' sizes");
+    let minified: u64 = report
+        .split(" -> ")
+        .nth(1)
+        .and_then(|rest| rest.split(' ').next())
+        .and_then(|n| n.parse().ok())
+        .unwrap();
+    let bundles: u64 = ["cjs/web.cjs", "cjs/web-bindings.cjs", "iife/index.js"]
+        .iter()
+        .map(|b| std::fs::metadata(out_dir.join(b)).unwrap().len())
+        .sum();
+    let wasm_base64 = std::fs::metadata(out_dir.join("esm/wasm-base64.js"))
+        .unwrap()
+        .len();
+    assert!(
+        minified < bundles - wasm_base64,
+        "{report} should leave out the embedded wasm ({bundles} bytes of bundles)"
+    );
+    assert!(!stdout.contains("Minified debug bundles"));
+    for bundle in ["cjs/web.cjs", "cjs/web-bindings.cjs", "iife/index.js"] {
+        let js = std::fs::read_to_string(out_dir.join(bundle)).unwrap();
+        assert!(
+            js.contains("//# sourceMappingURL="),
+            "minified {bundle} should link its source map"
+        );
+        assert!(out_dir.join(format!("{bundle}.map")).exists());
+        assert!(
+            !out_dir.join(bundle).with_extension("meta.json").exists(),
+            "esbuild's metafile for {bundle} should be removed"
+        );
+    }
+
+    // Minifying keeps the exports' names
+    let output = Command::new("node")
+        .args([
+            "-e",
+            "const m = require(process.argv[1]);\n\
+             if (m.greet.name !== 'greet') throw new Error(`greet is named ${m.greet.name}`);",
+            out_dir.join("cjs/web.cjs").to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to run node");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Unminified, they get no source map by default
+    let readable_dir = crate_path.join("dist-readable");
+    let output = run_wasm_bodge_build(
+        &crate_path,
+        &package_json,
+        &readable_dir,
+        &["--minify", "none"],
+    );
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Minified"));
+    for bundle in ["cjs/web.cjs", "cjs/web-bindings.cjs", "iife/index.js"] {
+        assert!(
+            !readable_dir.join(format!("{bundle}.map")).exists(),
+            "unminified {bundle} should have no source map by default"
+        );
+    }
+    assert!(!readable_dir.join("esm/node.js.map").exists());
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
 /// Passing `--debug-profile <name>` where `[profile.<name>]` is not declared
 /// fails with a wrapped error pointing the user at the required snippet.
 #[test]
@@ -1006,3 +1497,795 @@ fn test_debug_profile_release_migration() {
 
     let _ = std::fs::remove_dir_all(&crate_path);
 }
+
+/// `--wasm` skips cargo and runs wasm-bindgen on a prebuilt wasm, naming the
+/// output after `--wasm-name`.
+#[test]
+fn test_prebuilt_wasm_input() {
+    let crate_path = std::env::temp_dir().join("wasm-bodge-test-prebuilt-wasm");
+    let _ = std::fs::remove_dir_all(&crate_path);
+    copy_fixture_crate(&crate_path).unwrap();
+
+    // Produce the wasm outside of wasm-bodge, as an external build system would
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--release",
+            "--target",
+            "wasm32-unknown-unknown",
+            "--manifest-path",
+            crate_path.join("Cargo.toml").to_str().unwrap(),
+        ])
+        .status()
+        .expect("Failed to run cargo");
+    assert!(status.success(), "cargo build of fixture crate failed");
+
+    let prebuilt = crate_path.join("prebuilt.wasm");
+    std::fs::copy(
+        crate_path.join("target/wasm32-unknown-unknown/release/test_wasm_lib.wasm"),
+        &prebuilt,
+    )
+    .unwrap();
+    let original_bytes = std::fs::read(&prebuilt).unwrap();
+
+    let package_json = crate_path.join("package.json");
+    write_test_package_json(&package_json);
+    let out_dir = crate_path.join("dist");
+
+    // Point --crate-path somewhere without a Cargo.toml to prove cargo isn't used
+    let output = run_wasm_bodge_build(
+        &std::env::temp_dir(),
+        &package_json,
+        &out_dir,
+        &[
+            "--wasm",
+            prebuilt.to_str().unwrap(),
+            "--debug-wasm",
+            prebuilt.to_str().unwrap(),
+            "--wasm-name",
+            "test_wasm_lib",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "build failed:\nstdout: {stdout}\nstderr: {stderr}",
+    );
+
+    assert!(out_dir.join("wasm_bindgen/web/test_wasm_lib.js").exists());
+    assert!(
+        out_dir
+            .join("wasm_bindgen/web-debug/test_wasm_lib.js")
+            .exists()
+    );
+    assert!(out_dir.join("esm/debug-node.js").exists());
+    assert!(out_dir.join("test-wasm-lib.wasm").exists());
+    assert!(
+        !out_dir.join("wasm_bindgen/input").exists(),
+        "staging directory should be removed after wasm-bindgen"
+    );
+    assert_eq!(
+        std::fs::read(&prebuilt).unwrap(),
+        original_bytes,
+        "wasm-opt must not rewrite the caller's wasm in place"
+    );
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// `--asset-url-conditions` points the chosen conditions' ESM entrypoint at
+/// the asset-URL entrypoint and leaves CJS and the other conditions alone.
+#[test]
+fn test_asset_url_conditions() {
+    let Fixture {
+        crate_path,
+        package_json,
+        ..
+    } = build_fixture(
+        "asset-url-conditions",
+        &[],
+        &["--asset-url-conditions", "browser"],
+    );
+
+    let package: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&package_json).unwrap()).unwrap();
+    let root = &package["exports"]["."];
+    assert_eq!(
+        root["browser"]["import"]["default"],
+        "./dist/esm/asset-url.js"
+    );
+    assert_eq!(root["browser"]["require"]["default"], "./dist/cjs/web.cjs");
+    assert_eq!(root["import"]["default"], "./dist/esm/web.js");
+    assert_eq!(
+        package["exports"]["./asset-url"]["import"]["default"],
+        "./dist/esm/asset-url.js"
+    );
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// `[[conditions]]` in `wasm-bodge.toml` replace the default export condition
+/// mapping, in order, for both `.` and `./debug`.
+#[test]
+fn test_configured_conditions() {
+    let config = r#"
+[[conditions]]
+condition = "react-native"
+esm = "web"
+cjs = "web"
+
+[[conditions]]
+condition = "node"
+esm = "node"
+cjs = "node"
+
+[[conditions]]
+condition = "browser"
+esm = "web"
+cjs = "web"
+
+[[conditions]]
+condition = "import"
+esm = "web"
+
+[[conditions]]
+condition = "require"
+cjs = "web"
+"#;
+    let Fixture {
+        crate_path,
+        package_json,
+        out_dir,
+        ..
+    } = build_fixture(
+        "configured-conditions",
+        &[("wasm-bodge.toml", config)],
+        &["--debug-profile", "wasm-debug"],
+    );
+
+    let package: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&package_json).unwrap()).unwrap();
+    for (export, prefix) in [(".", ""), ("./debug", "debug-")] {
+        let root = package["exports"][export].as_object().unwrap();
+        let conditions: Vec<_> = root.keys().map(String::as_str).collect();
+        assert_eq!(
+            conditions,
+            ["react-native", "node", "browser", "import", "require"]
+        );
+        assert_eq!(
+            root["browser"]["import"]["default"],
+            format!("./dist/esm/{prefix}web.js")
+        );
+        assert_eq!(
+            root["require"]["default"],
+            format!("./dist/cjs/{prefix}web.cjs")
+        );
+    }
+
+    // Mistakes are caught before anything is built
+    std::fs::write(
+        crate_path.join("wasm-bodge.toml"),
+        "[[conditions]]\ncondition = \"browser\"\nesm = \"bundler\"\ncjs = \"bundler\"\n",
+    )
+    .unwrap();
+    let output = run_wasm_bodge_build(&crate_path, &package_json, &out_dir, &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no CommonJS entrypoint"));
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// `[[subpaths]]` in `wasm-bodge.toml` bundle hand-written modules into extra
+/// subpath exports, which import the bindings through the package's own name.
+#[test]
+fn test_custom_subpaths() {
+    let Fixture {
+        crate_path,
+        package_json,
+        out_dir,
+        ..
+    } = build_fixture(
+        "custom-subpaths",
+        &[
+            (
+                "js/shout.js",
+                "import { greet } from 'test-wasm-lib';\n\
+                 export function shout(name) { return greet(name).toUpperCase(); }\n",
+            ),
+            (
+                "js/shout.d.ts",
+                "export declare function shout(name: string): string;\n",
+            ),
+            (
+                "wasm-bodge.toml",
+                "[[subpaths]]\npath = \"./shout\"\nsource = \"js/shout.js\"\ntypes = \"js/shout.d.ts\"\n",
+            ),
+        ],
+        &[],
+    );
+
+    let package: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&package_json).unwrap()).unwrap();
+    assert_eq!(
+        package["exports"]["./shout"],
+        serde_json::json!({
+            "import": {
+                "types": "./dist/subpath-types/shout/shout.d.mts",
+                "default": "./dist/subpaths/shout.js"
+            },
+            "require": {
+                "types": "./dist/subpath-types/shout/shout.d.cts",
+                "default": "./dist/subpaths/shout.cjs"
+            }
+        })
+    );
+    for types in ["shout.d.ts", "shout.d.mts", "shout.d.cts"] {
+        assert!(out_dir.join("subpath-types/shout").join(types).exists());
+    }
+
+    // The package.json in the crate directory makes `test-wasm-lib`
+    // self-referencing, so both formats resolve the bindings by name.
+    std::fs::write(
+        crate_path.join("check.mjs"),
+        "import { shout } from 'test-wasm-lib/shout';\n\
+         if (shout('esm') !== 'HELLO, ESM!') throw new Error(shout('esm'));\n",
+    )
+    .unwrap();
+    std::fs::write(
+        crate_path.join("check.cjs"),
+        "const { shout } = require('test-wasm-lib/shout');\n\
+         if (shout('cjs') !== 'HELLO, CJS!') throw new Error(shout('cjs'));\n",
+    )
+    .unwrap();
+    for script in ["check.mjs", "check.cjs"] {
+        let status = Command::new("node")
+            .arg(script)
+            .current_dir(&crate_path)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{script} failed");
+    }
+
+    // Generated subpaths can't be taken over
+    std::fs::write(
+        crate_path.join("wasm-bodge.toml"),
+        "[[subpaths]]\npath = \"./slim\"\nsource = \"js/shout.js\"\n",
+    )
+    .unwrap();
+    let output = run_wasm_bodge_build(&crate_path, &package_json, &out_dir, &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("collides with a subpath"));
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// A `[facade]` replaces the bindings as what every entrypoint but `/slim`
+/// exports, and runs after the wasm is initialized.
+#[test]
+fn test_facade() {
+    let Fixture {
+        crate_path,
+        package_json,
+        ..
+    } = build_fixture(
+        "facade",
+        &[
+            // Calling into the wasm at the top level only works after init
+            (
+                "js/facade.js",
+                "import { greet, add } from 'wasm-bodge:bindings';\n\
+                 export const greeting = greet('facade');\n\
+                 export function sum(...xs) { return xs.reduce((a, b) => add(a, b), 0); }\n",
+            ),
+            (
+                "js/facade.d.ts",
+                "export declare const greeting: string;\n\
+                 export declare function sum(...xs: number[]): number;\n",
+            ),
+            (
+                "wasm-bodge.toml",
+                "[facade]\nsource = \"js/facade.js\"\ntypes = \"js/facade.d.ts\"\n",
+            ),
+        ],
+        &["--debug-profile", "wasm-debug"],
+    );
+
+    let package: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&package_json).unwrap()).unwrap();
+    assert_eq!(package["types"], "./dist/facade-types/facade.d.ts");
+    assert_eq!(
+        package["exports"]["./debug"]["import"]["types"],
+        "./dist/facade-types/facade.d.mts"
+    );
+    assert_eq!(
+        package["exports"]["./slim"]["require"]["types"],
+        "./dist/slim.d.cts"
+    );
+
+    std::fs::write(
+        crate_path.join("check.mjs"),
+        "import { greeting, sum } from 'test-wasm-lib';\n\
+         import * as debug from 'test-wasm-lib/debug';\n\
+         import * as slim from 'test-wasm-lib/slim';\n\
+         if (greeting !== 'Hello, facade!' || sum(1, 2, 3) !== 6) throw new Error('facade');\n\
+         if (debug.greeting !== 'Hello, facade!') throw new Error('debug facade');\n\
+         if (typeof slim.initSync !== 'function' || 'greeting' in slim) throw new Error('slim');\n",
+    )
+    .unwrap();
+    std::fs::write(
+        crate_path.join("check.cjs"),
+        "const { greeting, sum } = require('test-wasm-lib');\n\
+         if (greeting !== 'Hello, facade!' || sum(1, 2, 3) !== 6) throw new Error('facade');\n",
+    )
+    .unwrap();
+    for script in ["check.mjs", "check.cjs"] {
+        let status = Command::new("node")
+            .arg(script)
+            .current_dir(&crate_path)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{script} failed");
+    }
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// `[iife]` picks the script-tag bundle's global, wraps it in UMD and lowers
+/// it to an older esbuild target.
+#[test]
+fn test_iife_config() {
+    let Fixture {
+        crate_path,
+        package_json,
+        out_dir,
+        ..
+    } = build_fixture(
+        "iife-config",
+        &[(
+            "wasm-bodge.toml",
+            "[iife]\nglobal_name = \"TestLib\"\nformat = \"umd\"\ntarget = \"es2020\"\n",
+        )],
+        &["--debug-profile", "wasm-debug"],
+    );
+
+    assert_eq!(
+        std::fs::read_to_string(out_dir.join("iife/debug.d.ts")).unwrap(),
+        "/** The exports of the script-tag bundle. */\n\
+         declare var TestLibDebug: typeof import(\"../index.js\");\n"
+    );
+
+    // Without a loader the bundle sets the global; with AMD it defines a
+    // module instead
+    let script = "const vm = require('node:vm');\n\
+         const src = require('node:fs').readFileSync(process.argv[1], 'utf8');\n\
+         const page = { TextDecoder, TextEncoder, WebAssembly };\n\
+         page.self = page;\n\
+         vm.runInNewContext(src, page);\n\
+         if (page.TestLib.greet('umd') !== 'Hello, umd!') throw new Error('global failed');\n\
+         if (page.TestLib.__wasmBodge.environment !== 'iife') throw new Error('wrong environment');\n\
+         let defined;\n\
+         const amd = { TextDecoder, TextEncoder, WebAssembly };\n\
+         amd.self = amd;\n\
+         amd.define = (deps, factory) => { defined = factory(); };\n\
+         amd.define.amd = true;\n\
+         vm.runInNewContext(src, amd);\n\
+         if (amd.TestLib !== undefined || defined.greet('amd') !== 'Hello, amd!') throw new Error('amd failed');";
+    let output = Command::new("node")
+        .args([
+            "-e",
+            script,
+            out_dir.join("iife/index.js").to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to run node");
+    assert!(
+        output.status.success(),
+        "UMD bundle failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Globals have to be identifiers
+    std::fs::write(
+        crate_path.join("wasm-bodge.toml"),
+        "[iife]\nglobal_name = \"test-lib\"\n",
+    )
+    .unwrap();
+    let output = run_wasm_bodge_build(&crate_path, &package_json, &out_dir, &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not a JavaScript identifier"));
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// With `--development-conditions`, resolving the package under the
+/// `development` condition gets the debug variant, and otherwise the
+/// optimized one.
+#[test]
+fn test_development_conditions() {
+    let Fixture {
+        crate_path,
+        package_json,
+        out_dir,
+        ..
+    } = build_fixture(
+        "development-conditions",
+        &[],
+        &["--debug-profile", "wasm-debug", "--development-conditions"],
+    );
+
+    // The package.json in the crate directory makes `test-wasm-lib`
+    // self-referencing
+    for (conditions, variant) in [
+        (&["--conditions=development"][..], "debug"),
+        (&["--conditions=production"][..], "optimized"),
+        (&[][..], "optimized"),
+    ] {
+        for (flag, script) in [
+            (
+                "--input-type=module",
+                "const { __wasmBodge } = await import('test-wasm-lib');\n\
+                 console.log(__wasmBodge.variant);",
+            ),
+            (
+                "--input-type=commonjs",
+                "console.log(require('test-wasm-lib').__wasmBodge.variant);",
+            ),
+        ] {
+            let output = Command::new("node")
+                .args(conditions)
+                .args([flag, "-e", script])
+                .current_dir(&crate_path)
+                .output()
+                .expect("Failed to run node");
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout).trim(),
+                variant,
+                "{conditions:?} {flag}"
+            );
+        }
+    }
+
+    // Without a debug variant there's nothing to resolve `development` to
+    let output = run_wasm_bodge_build(
+        &crate_path,
+        &package_json,
+        &out_dir,
+        &["--development-conditions"],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("needs a debug variant"));
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// `--wasm-pack-dir` imports wasm-pack output for either the web or bundler
+/// target, generates the missing target's glue, and carries wasm-pack's
+/// package.json metadata over to a fresh package.json.
+#[test]
+fn test_wasm_pack_import() {
+    let crate_path = std::env::temp_dir().join("wasm-bodge-test-wasm-pack");
+    let _ = std::fs::remove_dir_all(&crate_path);
+    copy_fixture_crate(&crate_path).unwrap();
+
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--release",
+            "--target",
+            "wasm32-unknown-unknown",
+            "--manifest-path",
+            crate_path.join("Cargo.toml").to_str().unwrap(),
+        ])
+        .status()
+        .expect("Failed to run cargo");
+    assert!(status.success(), "cargo build of fixture crate failed");
+    let wasm = crate_path.join("target/wasm32-unknown-unknown/release/test_wasm_lib.wasm");
+
+    for target in ["web", "bundler"] {
+        // Recreate what `wasm-pack build --target <target>` leaves in pkg/
+        let pkg_dir = crate_path.join(format!("pkg-{target}"));
+        let status = Command::new("wasm-bindgen")
+            .args([
+                wasm.to_str().unwrap(),
+                "--out-dir",
+                pkg_dir.to_str().unwrap(),
+                "--target",
+                target,
+            ])
+            .status()
+            .expect("Failed to run wasm-bindgen");
+        assert!(status.success(), "wasm-bindgen --target {target} failed");
+        std::fs::write(
+            pkg_dir.join("package.json"),
+            r#"{
+  "name": "test-wasm-lib",
+  "version": "1.2.3",
+  "license": "MIT",
+  "files": ["test_wasm_lib_bg.wasm"],
+  "module": "test_wasm_lib.js",
+  "sideEffects": ["./snippets/*"]
+}
+"#,
+        )
+        .unwrap();
+
+        let package_dir = crate_path.join(format!("package-{target}"));
+        std::fs::create_dir_all(&package_dir).unwrap();
+        let package_json = package_dir.join("package.json");
+        let out_dir = package_dir.join("dist");
+
+        let output = run_wasm_bodge_build(
+            &crate_path,
+            &package_json,
+            &out_dir,
+            &["--wasm-pack-dir", pkg_dir.to_str().unwrap()],
+        );
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            output.status.success(),
+            "build from wasm-pack {target} output failed:\nstdout: {stdout}\nstderr: {stderr}",
+        );
+
+        let package: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&package_json).unwrap()).unwrap();
+        assert_eq!(package["version"], "1.2.3");
+        assert_eq!(package["module"], "./dist/esm/bundler.js");
+        assert!(package["exports"]["."].is_object());
+
+        for dir in ["web", "bundler"] {
+            assert!(
+                out_dir
+                    .join(format!("wasm_bindgen/{dir}/test_wasm_lib_bg.wasm"))
+                    .exists(),
+                "{dir} target missing when importing wasm-pack {target} output"
+            );
+        }
+
+        let output = Command::new("node")
+            .args([
+                "--input-type=module",
+                "-e",
+                "const m = await import(process.argv[1]); \
+                 if (m.add(2, 3) !== 5) throw new Error('add failed');",
+                out_dir.join("esm/node.js").to_str().unwrap(),
+            ])
+            .output()
+            .expect("Failed to run node");
+        assert!(
+            output.status.success(),
+            "esm/node.js from wasm-pack {target} output failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// `--compress-wasm gzip` embeds a gzipped payload in the base64 entrypoints
+/// (smaller than the raw base64 module) and they still initialize.
+#[test]
+fn test_compressed_embedded_wasm() {
+    // Unminified, to find the embedded module's export in the IIFE
+    let Fixture {
+        crate_path,
+        out_dir,
+        stdout,
+        ..
+    } = build_fixture(
+        "compress-wasm",
+        &[],
+        &["--compress-wasm", "gzip", "--minify", "none"],
+    );
+    assert!(stdout.contains("Compressed optimized wasm with gzip"));
+
+    let raw = std::fs::metadata(out_dir.join("esm/wasm-base64.js")).unwrap();
+    let gzip = std::fs::metadata(out_dir.join("esm/wasm-base64-gzip.js")).unwrap();
+    assert!(gzip.len() < raw.len(), "gzip payload should be smaller");
+
+    // The IIFE bundle should carry only the compressed payload
+    let iife = std::fs::read_to_string(out_dir.join("iife/index.js")).unwrap();
+    assert!(iife.contains("wasmBase64Gzip"));
+    assert!(!iife.contains("wasmBase64 ="));
+
+    for (flag, entry, entrypoint) in [
+        (
+            "--input-type=module",
+            "await import(process.argv[1])",
+            out_dir.join("esm/web.js"),
+        ),
+        (
+            "--input-type=commonjs",
+            "require(process.argv[1])",
+            out_dir.join("cjs/web.cjs"),
+        ),
+    ] {
+        let script = format!(
+            "const m = {entry}; if (m.greet('gzip') !== 'Hello, gzip!') throw new Error('greet failed');"
+        );
+        let output = Command::new("node")
+            .args([flag, "-e", &script, entrypoint.to_str().unwrap()])
+            .output()
+            .expect("Failed to run node");
+        assert!(
+            output.status.success(),
+            "{} failed: {}",
+            entrypoint.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+#[test]
+fn test_async_init() {
+    // A zero threshold makes `--async-init auto` pick async for any wasm;
+    // gzip exercises the DecompressionStream path.
+    let Fixture {
+        crate_path,
+        out_dir,
+        stdout,
+        ..
+    } = build_fixture(
+        "async-init",
+        &[],
+        &["--async-init-threshold", "0", "--compress-wasm", "gzip"],
+    );
+    assert!(stdout.contains("Using async init for Optimized"));
+
+    let esm = std::fs::read_to_string(out_dir.join("esm/web.js")).unwrap();
+    assert!(esm.contains("await ready;"));
+    assert!(!esm.contains("initSync"));
+    assert!(
+        !out_dir.join("esm/web.bundle-input.js").exists(),
+        "temporary esbuild input should be removed"
+    );
+    let iife = std::fs::read_to_string(out_dir.join("iife/index.js")).unwrap();
+    assert!(iife.contains("ready"));
+
+    // ESM is ready once imported; CJS has to wait for `ready`
+    for (flag, script, entrypoint) in [
+        (
+            "--input-type=module",
+            "const m = await import(process.argv[1]);\n\
+             if (m.greet('async') !== 'Hello, async!') throw new Error('greet failed');",
+            out_dir.join("esm/web.js"),
+        ),
+        (
+            "--input-type=commonjs",
+            "const m = require(process.argv[1]);\n\
+             m.ready.then(() => {\n\
+               if (m.greet('async') !== 'Hello, async!') throw new Error('greet failed');\n\
+             });",
+            out_dir.join("cjs/web.cjs"),
+        ),
+    ] {
+        let output = Command::new("node")
+            .args([flag, "-e", script, entrypoint.to_str().unwrap()])
+            .output()
+            .expect("Failed to run node");
+        assert!(
+            output.status.success(),
+            "{} failed: {}",
+            entrypoint.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// With `--wasm-source-override`, the base64 entrypoints initialize from the
+/// wasm in `globalThis.__wasmBodgeWasm` and only fall back to the embedded
+/// copy without it.
+#[test]
+fn test_wasm_source_override() {
+    let Fixture {
+        crate_path,
+        out_dir,
+        ..
+    } = build_fixture("wasm-source-override", &[], &["--wasm-source-override"]);
+
+    let esm = std::fs::read_to_string(out_dir.join("esm/web.js")).unwrap();
+    assert!(esm.contains("import('./wasm-base64.js')"));
+    assert!(out_dir.join("esm/wasm-source.js").exists());
+
+    // A precompiled module for ESM, bytes for CJS, and the embedded copy
+    // when the page provides nothing
+    let wasm = out_dir.join("wasm_bindgen/web/test_wasm_lib_bg.wasm");
+    let provide_module = "const { readFileSync } = require('node:fs');\n\
+         globalThis.__wasmBodgeWasm = {\n\
+           'test-wasm-lib': new WebAssembly.Module(readFileSync(process.argv[2])),\n\
+         };\n";
+    let provide_bytes = "const { readFileSync } = require('node:fs');\n\
+         globalThis.__wasmBodgeWasm = { 'test-wasm-lib': readFileSync(process.argv[2]) };\n";
+    let check_esm = "import(process.argv[1]).then((m) => {\n\
+           if (m.greet('override') !== 'Hello, override!') throw new Error('greet failed');\n\
+           if (m.__wasmBodge.name !== 'test-wasm-lib') throw new Error('wrong name');\n\
+         });";
+    let check_cjs = "const m = require(process.argv[1]);\n\
+         m.ready.then(() => {\n\
+           if (m.greet('override') !== 'Hello, override!') throw new Error('greet failed');\n\
+         });";
+    for (provide, check, entrypoint) in [
+        (provide_module, check_esm, out_dir.join("esm/web.js")),
+        (provide_bytes, check_cjs, out_dir.join("cjs/web.cjs")),
+        ("", check_esm, out_dir.join("esm/web.js")),
+    ] {
+        let output = Command::new("node")
+            .args([
+                "--input-type=commonjs",
+                "-e",
+                &format!("{provide}{check}"),
+                entrypoint.to_str().unwrap(),
+                wasm.to_str().unwrap(),
+            ])
+            .output()
+            .expect("Failed to run node");
+        assert!(
+            output.status.success(),
+            "{} failed: {}",
+            entrypoint.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    // Anything else the page provides is what gets instantiated
+    let output = Command::new("node")
+        .args([
+            "--input-type=commonjs",
+            "-e",
+            &format!(
+                "globalThis.__wasmBodgeWasm = {{ 'test-wasm-lib': new Uint8Array([0]) }};\n{check_esm}"
+            ),
+            out_dir.join("esm/web.js").to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to run node");
+    assert!(!output.status.success());
+
+    let _ = std::fs::remove_dir_all(&crate_path);
+}
+
+/// Check the base64-embedded entrypoints' decoder on every fallback path and
+/// print its timings against the `atob` + `charCodeAt` baseline. Run with
+/// `--nocapture` to see the numbers.
+#[test]
+fn test_base64_decode_benchmark() {
+    let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let output = Command::new("node")
+        .arg(project_root.join("tests/benchmarks/base64_decode.mjs"))
+        .output()
+        .expect("Failed to run node");
+    println!("{}", String::from_utf8_lossy(&output.stdout));
+    assert!(
+        output.status.success(),
+        "base64 decode benchmark failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Round-trip sync-flushed gzip streams with stored blocks through the
+/// bundled gzip decoder, and print its timings against
+/// `DecompressionStream`. Run with `--nocapture` to see the numbers.
+#[test]
+fn test_gunzip() {
+    let project_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let output = Command::new("node")
+        .arg(project_root.join("tests/benchmarks/gunzip.mjs"))
+        .output()
+        .expect("Failed to run node");
+    println!("{}", String::from_utf8_lossy(&output.stdout));
+    assert!(
+        output.status.success(),
+        "gunzip round trip failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
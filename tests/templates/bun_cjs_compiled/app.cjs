@@ -0,0 +1,12 @@
+// Compiled into a single-file executable by `npm run build`, loading the
+// package with `require` so Bun resolves the "bun" condition's CJS entrypoint
+const { add, greet, __wasmBodge } = require('test-wasm-lib');
+
+if (add(2, 3) !== 5 || greet('Bun') !== 'Hello, Bun!') {
+  throw new Error('compiled executable returned wrong results');
+}
+if (__wasmBodge.environment !== 'bun') {
+  throw new Error(`expected the bun entrypoint, got ${__wasmBodge.environment}`);
+}
+
+console.log('WASM_BODGE_TEST_PASSED');
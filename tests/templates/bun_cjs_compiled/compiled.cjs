@@ -0,0 +1,18 @@
+// Run the `bun build --compile` executable from another directory with the
+// package's wasm moved away, so it only passes if the wasm was embedded.
+const { execFileSync } = require('node:child_process');
+const { renameSync } = require('node:fs');
+const { tmpdir } = require('node:os');
+const { resolve } = require('node:path');
+
+const dist = resolve('node_modules/test-wasm-lib/dist');
+renameSync(dist, `${dist}.moved`);
+try {
+  const output = execFileSync(resolve('out/test'), { cwd: tmpdir(), encoding: 'utf8' });
+  if (!output.includes('WASM_BODGE_TEST_PASSED')) {
+    throw new Error(`compiled executable failed:\n${output}`);
+  }
+  console.log(output.trim());
+} finally {
+  renameSync(`${dist}.moved`, dist);
+}
@@ -0,0 +1,8 @@
+// Compiled into a single-file executable by `npm run build`
+import { add, greet } from 'test-wasm-lib';
+
+if (add(2, 3) !== 5 || greet('Bun') !== 'Hello, Bun!') {
+  throw new Error('compiled executable returned wrong results');
+}
+
+console.log('WASM_BODGE_TEST_PASSED');
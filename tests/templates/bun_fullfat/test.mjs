@@ -0,0 +1,23 @@
+import { add, greet } from 'test-wasm-lib';
+import * as debug from 'test-wasm-lib/debug';
+
+const entry = import.meta.resolve('test-wasm-lib');
+if (!entry.endsWith('/esm/bun.js')) {
+  throw new Error(`expected the bun condition to resolve to esm/bun.js, got ${entry}`);
+}
+
+const result1 = add(2, 3);
+if (result1 !== 5) {
+  throw new Error(`add(2, 3) expected 5, got ${result1}`);
+}
+
+const result2 = greet('World');
+if (result2 !== 'Hello, World!') {
+  throw new Error(`greet("World") expected "Hello, World!", got ${result2}`);
+}
+
+if (debug.greet('Debug') !== 'Hello, Debug!') {
+  throw new Error('debug greet failed');
+}
+
+console.log('WASM_BODGE_TEST_PASSED');
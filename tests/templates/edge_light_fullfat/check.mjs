@@ -0,0 +1,57 @@
+// Wrangler stands in for Vercel's edge runtime: both compile `.wasm?module`
+// imports ahead of time. Check the edge-light entrypoint was the one bundled,
+// then serve the worker with `wrangler dev` and check what it responds.
+import { spawn } from 'node:child_process';
+import { readdirSync, readFileSync } from 'node:fs';
+import { createServer } from 'node:net';
+
+const files = readdirSync('dist');
+if (!files.some((f) => f.endsWith('.wasm'))) {
+  throw new Error(`expected a precompiled wasm module in dist, got ${files.join(', ')}`);
+}
+const bundle = readFileSync('dist/worker.js', 'utf8');
+if (bundle.includes('wasmBase64')) {
+  throw new Error('edge-light bundle should not embed base64 wasm');
+}
+
+const port = await new Promise((resolve) => {
+  const server = createServer().listen(0, '127.0.0.1', () => {
+    const { port } = server.address();
+    server.close(() => resolve(port));
+  });
+});
+
+// Its own process group, so killing it also stops the workerd it starts
+const wrangler = spawn('wrangler', ['dev', '--ip', '127.0.0.1', '--port', String(port)], {
+  env: {
+    ...process.env,
+    WRANGLER_BUILD_CONDITIONS: 'edge-light,worker,browser',
+    WRANGLER_SEND_METRICS: 'false',
+  },
+  stdio: ['ignore', 'pipe', 'pipe'],
+  detached: true,
+});
+let log = '';
+wrangler.stdout.on('data', (data) => (log += data));
+wrangler.stderr.on('data', (data) => (log += data));
+let exited = false;
+wrangler.on('exit', () => (exited = true));
+
+try {
+  let body;
+  for (const deadline = Date.now() + 60_000; body === undefined; ) {
+    if (exited) throw new Error(`wrangler dev exited:\n${log}`);
+    if (Date.now() > deadline) throw new Error(`worker didn't respond in time:\n${log}`);
+    try {
+      body = await (await fetch(`http://127.0.0.1:${port}/`)).text();
+    } catch {
+      await new Promise((resolve) => setTimeout(resolve, 500));
+    }
+  }
+  if (!body.includes('WASM_BODGE_TEST_PASSED')) {
+    throw new Error(`worker responded: ${body}`);
+  }
+  console.log(body);
+} finally {
+  if (!exited) process.kill(-wrangler.pid);
+}
@@ -1,4 +1,4 @@
-const { add, greet } = require('test-wasm-lib');
+const { add, greet, __wasmBodge } = require('test-wasm-lib');
 
 const result1 = add(2, 3);
 if (result1 !== 5) {
@@ -10,4 +10,14 @@ if (result2 !== 'Hello, World!') {
   throw new Error(`greet("World") expected "Hello, World!", got ${result2}`);
 }
 
+if (
+  __wasmBodge.variant !== 'optimized' ||
+  __wasmBodge.environment !== 'node' ||
+  __wasmBodge.strategy !== 'NodeFsSync' ||
+  __wasmBodge.version !== '0.1.0' ||
+  !/^[0-9a-f]{64}$/.test(__wasmBodge.wasmHash)
+) {
+  throw new Error(`unexpected __wasmBodge: ${JSON.stringify(__wasmBodge)}`);
+}
+
 console.log('WASM_BODGE_TEST_PASSED');
@@ -0,0 +1,29 @@
+// Test that a CommonJS module and an ES module in the same process share
+// one wasm instance. The test package is built with --shared-node-instance,
+// so the Node ESM entrypoints re-export the CJS ones.
+
+const { add } = require('test-wasm-lib');
+const { greet } = require('test-wasm-lib/slim');
+
+// add comes from root (auto-initialized)
+const sum = add(2, 3);
+if (sum !== 5) {
+  throw new Error(`Expected add(2, 3) = 5, got ${sum}`);
+}
+
+// greet comes from slim (should work without manual init)
+const greeting = greet('World');
+if (greeting !== 'Hello, World!') {
+  throw new Error(`Expected greet('World') = 'Hello, World!', got ${greeting}`);
+}
+
+// An ES module importing the package gets the same functions
+Promise.all([import('test-wasm-lib'), import('test-wasm-lib/slim')]).then(([root, slim]) => {
+  if (root.add !== add) {
+    throw new Error('require and import of the root export loaded separate instances');
+  }
+  if (slim.greet !== greet || typeof slim.default !== 'function') {
+    throw new Error('require and import of /slim loaded separate instances');
+  }
+  console.log('WASM_BODGE_TEST_PASSED');
+});
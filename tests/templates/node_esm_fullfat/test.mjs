@@ -1,4 +1,4 @@
-import { add, greet } from 'test-wasm-lib';
+import { add, greet, __wasmBodge } from 'test-wasm-lib';
 
 const result1 = add(2, 3);
 if (result1 !== 5) {
@@ -10,4 +10,15 @@ if (result2 !== 'Hello, World!') {
   throw new Error(`greet("World") expected "Hello, World!", got ${result2}`);
 }
 
+if (
+  __wasmBodge.variant !== 'optimized' ||
+  __wasmBodge.environment !== 'node' ||
+  __wasmBodge.strategy !== 'NodeFsSync' ||
+  __wasmBodge.version !== '0.1.0' ||
+  !/^[0-9a-f]{64}$/.test(__wasmBodge.wasmHash)
+) {
+  throw new Error(`unexpected __wasmBodge: ${JSON.stringify(__wasmBodge)}`);
+}
+
+await __wasmBodge.ready;
 console.log('WASM_BODGE_TEST_PASSED');
@@ -0,0 +1,30 @@
+// Test that an ES module and a CommonJS dependency in the same process
+// share one wasm instance. The test package is built with
+// --shared-node-instance, so the Node ESM entrypoints re-export the CJS ones.
+
+import { createRequire } from 'node:module';
+import { add } from 'test-wasm-lib';
+import { greet } from 'test-wasm-lib/slim';
+
+// add comes from root (auto-initialized)
+const sum = add(2, 3);
+if (sum !== 5) {
+  throw new Error(`Expected add(2, 3) = 5, got ${sum}`);
+}
+
+// greet comes from slim (should work without manual init)
+const greeting = greet('World');
+if (greeting !== 'Hello, World!') {
+  throw new Error(`Expected greet('World') = 'Hello, World!', got ${greeting}`);
+}
+
+// A CommonJS dependency requiring the package gets the same functions
+const require = createRequire(import.meta.url);
+if (require('test-wasm-lib').add !== add) {
+  throw new Error('import and require of the root export loaded separate instances');
+}
+if (require('test-wasm-lib/slim').greet !== greet) {
+  throw new Error('import and require of /slim loaded separate instances');
+}
+
+console.log('WASM_BODGE_TEST_PASSED');
@@ -0,0 +1,7 @@
+import { defineConfig } from 'vite';
+
+// No wasm or top-level-await plugins: Vite emits the asset-url entrypoint's
+// `new URL(..., import.meta.url)` wasm as an asset.
+export default defineConfig({
+  build: { target: 'esnext' },
+});
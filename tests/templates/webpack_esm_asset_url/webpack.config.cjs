@@ -0,0 +1,18 @@
+const path = require('path');
+const CopyPlugin = require('copy-webpack-plugin');
+
+// No experiments.asyncWebAssembly: the asset-url entrypoint references the
+// wasm with `new URL(..., import.meta.url)`, which webpack emits as an asset.
+module.exports = {
+  entry: './main.js',
+  output: {
+    filename: 'bundle.js',
+    path: path.resolve(__dirname, 'dist'),
+  },
+  plugins: [
+    new CopyPlugin({
+      patterns: [{ from: 'index.html', to: 'index.html' }],
+    }),
+  ],
+  mode: 'production',
+};
@@ -0,0 +1,19 @@
+import { add, greet, ready, __wasmBodge } from 'test-wasm-lib';
+
+if (__wasmBodge.environment !== 'worker' || __wasmBodge.strategy !== 'Base64Async') {
+  throw new Error(`expected the worker entrypoint, got ${JSON.stringify(__wasmBodge)}`);
+}
+
+// The entrypoint awaits `ready` at the top level, so the wasm is usable now
+const result1 = add(2, 3);
+if (result1 !== 5) {
+  throw new Error(`add(2, 3) expected 5, got ${result1}`);
+}
+
+const result2 = greet('World');
+if (result2 !== 'Hello, World!') {
+  throw new Error(`greet("World") expected "Hello, World!", got ${result2}`);
+}
+
+await ready;
+console.log('WASM_BODGE_TEST_PASSED');
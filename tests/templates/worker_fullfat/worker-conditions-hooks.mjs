@@ -0,0 +1,5 @@
+// Resolve with a worker runtime's conditions instead of Node's, which would
+// otherwise pick the "node" condition first.
+export async function resolve(specifier, context, nextResolve) {
+  return nextResolve(specifier, { ...context, conditions: ['worker', 'browser', 'import'] });
+}
@@ -0,0 +1,5 @@
+// Node stands in for a generic WinterCG runtime resolving "worker": unlike
+// workerd it compiles wasm from bytes, which the worker entrypoint needs.
+import { register } from 'node:module';
+
+register('./worker-conditions-hooks.mjs', import.meta.url);